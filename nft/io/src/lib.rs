@@ -4,6 +4,26 @@ use gstd::{prelude::*, ActorId};
 
 pub type TokenId = u128;
 pub const ZERO_ID: ActorId = ActorId::zero();
+/// Upper bound for royalties in basis points (10%)
+pub const ROYALTY_BPS_MAX: u16 = 1_000;
+/// Maximum number of records per `StateQuery::ExportRecords` page and `NftAction::ImportRecords` chunk
+pub const MIGRATION_CHUNK_LIMIT: u64 = 100;
+/// Upper bound for the marketplace fee in basis points (10%)
+pub const PLATFORM_FEE_BPS_MAX: u16 = 1_000;
+/// Maximum number of listings per `StateQuery::Listings` page
+pub const LISTINGS_PAGE_LIMIT: u64 = 100;
+/// Maximum length of a display name set by a token owner
+pub const DISPLAY_NAME_MAX_LEN: usize = 64;
+/// Maximum number of attributes per token
+pub const TOKEN_ATTRIBUTES_MAX: usize = 32;
+/// Maximum number of tokens per `NftAction::SetAttributes` batch
+pub const ATTRIBUTES_BATCH_LIMIT: usize = 100;
+/// Upper bound for `Collection::uri_padding`, the number of digits of `TokenId::MAX`
+pub const URI_PADDING_MAX: u8 = 39;
+/// Maximum length of a link or a category in `Collection`
+pub const COLLECTION_FIELD_MAX_LEN: usize = 256;
+/// Maximum number of `Collection::social_links`
+pub const SOCIAL_LINKS_MAX: usize = 8;
 
 pub struct NftMetadata;
 
@@ -11,12 +31,12 @@ impl Metadata for NftMetadata {
     type Init = In<InitNft>;
     type Handle = InOut<NftAction, NftEvent>;
     type Reply = ();
-    type Others = ();
+    type Others = InOut<NftReceiverAction, NftReceiverReply>;
     type Signal = ();
     type State = InOut<StateQuery, StateReply>;
 }
 
-#[derive(Default, Debug, Encode, Decode, TypeInfo)]
+#[derive(Debug, Encode, Decode, TypeInfo)]
 pub struct Config {
     pub supply_limit: u128,
     pub mint_price: u128,
@@ -25,6 +45,50 @@ pub struct Config {
     pub sale_time: u64,
     pub dev_wallet: ActorId,
     pub withdraw_wallet: ActorId,
+    // `false` binds every token of the collection to its owner
+    pub transferable: bool,
+    // default royalty for secondary sales, in basis points of the sale price
+    pub royalty_receiver: ActorId,
+    pub royalty_bps: u16,
+    // approved accounts and operators can only move tokens with `SaleTransfer`
+    pub royalty_enforced: bool,
+    // fee paid to `dev_wallet` on listing sales, in basis points of the sale price
+    pub platform_fee_bps: u16,
+    // other collections whose tokens can hold tokens of this one
+    pub nesting_collections: Vec<ActorId>,
+    // reward points per staked token per second
+    pub staking_rate: u128,
+    // fungible token program paying one token unit per point on `ClaimRewards`
+    pub reward_token: Option<ActorId>,
+    // account allowed to update token metadata along with the collection owner
+    pub metadata_manager: ActorId,
+    // token owners and their delegates can set the display name of their tokens
+    pub owner_metadata_updates: bool,
+}
+
+impl Default for Config {
+    /// Same as the derived default, except that the collection is transferable
+    fn default() -> Self {
+        Self {
+            supply_limit: 0,
+            mint_price: 0,
+            mint_fee: 0,
+            mint_limit: 0,
+            sale_time: 0,
+            dev_wallet: ZERO_ID,
+            withdraw_wallet: ZERO_ID,
+            transferable: true,
+            royalty_receiver: ZERO_ID,
+            royalty_bps: 0,
+            royalty_enforced: false,
+            platform_fee_bps: 0,
+            nesting_collections: Vec::new(),
+            staking_rate: 0,
+            reward_token: None,
+            metadata_manager: ZERO_ID,
+            owner_metadata_updates: false,
+        }
+    }
 }
 
 #[derive(Default, Debug, Encode, Decode, TypeInfo)]
@@ -33,14 +97,48 @@ pub struct InitNft {
     pub config: Config,
 }
 
-#[derive(Default, Debug, Encode, Decode, TypeInfo)]
+#[derive(Default, Debug, Clone, Encode, Decode, TypeInfo)]
 pub struct Collection {
     pub name: String,
     pub description: String,
     pub symbol: String,
+    // prefix of the token URIs
     pub base_uri: String,
+    // appended to the token id in the token URIs, ex. ".json"
+    pub uri_suffix: String,
+    // minimal number of digits of the token id in the token URIs, padded with zeros
+    pub uri_padding: u8,
+    // image links shown by marketplaces
+    pub banner: String,
+    pub logo: String,
+    // website of the project
+    pub external_link: String,
+    pub social_links: Vec<SocialLink>,
+    // ex. "art", "gaming"
+    pub category: String,
+    // link to the contract-level metadata JSON
+    pub contract_uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct SocialLink {
+    // ex. "twitter", "discord"
+    pub platform: String,
+    pub url: String,
+}
+
+// `None` keeps the current value, empty strings clear the links
+#[derive(Default, Debug, Encode, Decode, TypeInfo)]
+pub struct CollectionUpdate {
+    pub banner: Option<String>,
+    pub logo: Option<String>,
+    pub external_link: Option<String>,
+    pub social_links: Option<Vec<SocialLink>>,
+    pub category: Option<String>,
+    pub contract_uri: Option<String>,
 }
 
+/// New variants go to the end, deployed clients rely on the SCALE indices of the existing ones
 #[derive(Debug, Encode, Decode, TypeInfo)]
 pub enum NftAction {
     Buy {
@@ -63,6 +161,7 @@ pub enum NftAction {
     Approve {
         to: ActorId,
         token_id: TokenId,
+        expires_at: Option<u64>,
     },
     GetOwner {
         token_id: TokenId,
@@ -103,8 +202,242 @@ pub enum NftAction {
         sale_time: u64,
     },
     ToggleSaleActive {},
+
+    // Circuit breaker
+    Pause {
+        scope: PauseScope,
+    },
+    Unpause {
+        scope: PauseScope,
+    },
+
+    // Migration
+    // cancels auctions and refunds open offers and bids, fails while safe transfers are pending
+    Freeze {},
+    Unfreeze {},
+    BeginImport {
+        header: MigrationHeader,
+    },
+    ImportRecords {
+        records: Vec<MigrationRecord>,
+    },
+    FinishImport {},
+
+    // Approvals
+    SetApprovalForAll {
+        operator: ActorId,
+        approved: bool,
+    },
+    RevokeApproval {
+        token_id: TokenId,
+    },
+
+    // Transfers
+    BatchTransfer {
+        transfers: Vec<(ActorId, TokenId)>,
+    },
+    // the runtime can't tell programs from accounts, so the caller states it:
+    // `to_program: false` transfers right away, `true` waits for `NftReceiverReply::Accepted`
+    SafeTransfer {
+        to: ActorId,
+        token_id: TokenId,
+        data: Vec<u8>,
+        to_program: bool,
+    },
+
+    // Minting
+    Airdrop {
+        to: ActorId,
+        amount: u128,
+        soulbound: bool,
+    },
+
+    // Locks
+    Lock {
+        token_id: TokenId,
+        until: u64,
+        locker: ActorId,
+    },
+    Unlock {
+        token_id: TokenId,
+    },
+
+    // Royalties
+    SetDefaultRoyalty {
+        receiver: ActorId,
+        bps: u16,
+    },
+    SetTokenRoyalty {
+        token_id: TokenId,
+        royalty: Option<Royalty>,
+    },
+    // `price` is trusted from the caller, operators are expected to pass the real sale price
+    SaleTransfer {
+        to: ActorId,
+        token_id: TokenId,
+        price: u128,
+    },
+    SetRoyaltyEnforced {
+        enforced: bool,
+    },
+
+    // Marketplace
+    List {
+        token_id: TokenId,
+        price: u128,
+        expires_at: Option<u64>,
+    },
+    Delist {
+        token_id: TokenId,
+    },
+    BuyListed {
+        token_id: TokenId,
+    },
+    SetPlatformFee {
+        bps: u16,
+    },
+    // the attached value is escrowed, `token_id: None` bids on any token of the collection
+    MakeOffer {
+        token_id: Option<TokenId>,
+        expires_at: Option<u64>,
+    },
+    CancelOffer {
+        token_id: Option<TokenId>,
+    },
+    AcceptOffer {
+        token_id: TokenId,
+        buyer: ActorId,
+        // accept the collection-wide offer of `buyer` instead of its offer on `token_id`
+        collection_offer: bool,
+    },
+    StartAuction {
+        token_id: TokenId,
+        reserve_price: u128,
+        min_increment: u128,
+        ends_at: u64,
+        // bids in the last `extension` seconds push the end to `extension` seconds after the bid
+        extension: u64,
+    },
+    // the attached value is escrowed until the bid is outbid, the auction is settled or the program is frozen
+    Bid {
+        token_id: TokenId,
+    },
+    SettleAuction {
+        token_id: TokenId,
+    },
+    CancelAuction {
+        token_id: TokenId,
+    },
+
+    // Rentals
+    // `user: ZERO_ID` removes the user
+    SetUser {
+        token_id: TokenId,
+        user: ActorId,
+        expires: u64,
+    },
+    // `terms: None` stops renting the token out
+    SetRentalTerms {
+        token_id: TokenId,
+        terms: Option<RentalTerms>,
+    },
+    // paid with the attached value
+    Rent {
+        token_id: TokenId,
+        duration: u64,
+    },
+
+    // Delegation
+    // `token_ids: None` delegates every token of `msg::source()`
+    Delegate {
+        delegate: ActorId,
+        token_ids: Option<Vec<TokenId>>,
+        expires_at: Option<u64>,
+    },
+    RevokeDelegate {
+        delegate: ActorId,
+    },
+
+    // Nesting
+    // a parent from another collection is looked up in that collection first
+    Nest {
+        token_id: TokenId,
+        parent: TokenParent,
+    },
+    Unnest {
+        token_id: TokenId,
+    },
+    SetNestingCollection {
+        collection: ActorId,
+        allowed: bool,
+    },
+
+    // Staking
+    Stake {
+        token_ids: Vec<TokenId>,
+    },
+    Unstake {
+        token_ids: Vec<TokenId>,
+    },
+    // pays the points of `owner` in `Config::reward_token`, can be called by its delegates
+    ClaimRewards {
+        owner: ActorId,
+    },
+    SetStakingRate {
+        rate: u128,
+    },
+    SetRewardToken {
+        reward_token: Option<ActorId>,
+    },
+
+    // Token metadata
+    // a non-empty `reference` overrides the token URI
+    SetTokenMetadata {
+        token_id: TokenId,
+        metadata: TokenMetadata,
+    },
+    SetDisplayName {
+        token_id: TokenId,
+        name: String,
+    },
+    // announces an off-chain update of the tokens in the range, both ends included
+    RefreshMetadata {
+        from_token_id: TokenId,
+        to_token_id: TokenId,
+    },
+    SetMetadataManager {
+        manager: ActorId,
+    },
+    SetOwnerMetadataUpdates {
+        enabled: bool,
+    },
+    // replaces all attributes of every listed token
+    SetAttributes {
+        attributes: Vec<(TokenId, Vec<Attribute>)>,
+    },
+    SetUriTemplate {
+        suffix: String,
+        padding: u8,
+    },
+    // `uri: None` falls back to the collection URI template
+    SetTokenUri {
+        token_id: TokenId,
+        uri: Option<String>,
+    },
+    // permanent, the metadata and URI of the token can't change anymore
+    FreezeMetadata {
+        token_id: TokenId,
+    },
+    // permanent, freezes every existing and future token
+    FreezeAllMetadata {},
+
+    // Collection metadata
+    UpdateCollection {
+        update: CollectionUpdate,
+    },
 }
 
+/// New variants go to the end, deployed clients rely on the SCALE indices of the existing ones
 #[derive(Debug, Encode, Decode, TypeInfo)]
 pub enum NftEvent {
     Bought {
@@ -131,6 +464,7 @@ pub enum NftEvent {
         owner: ActorId,
         approved_account: ActorId,
         token_id: TokenId,
+        expires_at: Option<u64>,
     },
     Owner {
         owner: ActorId,
@@ -178,6 +512,547 @@ pub enum NftEvent {
     },
 
     TransferValue,
+
+    // Circuit breaker events
+    Paused {
+        scope: PauseScope,
+    },
+    Unpaused {
+        scope: PauseScope,
+    },
+
+    // Migration events
+    Frozen,
+    Unfrozen,
+    ImportStarted {
+        records: u64,
+        checksum: u64,
+    },
+    RecordsImported {
+        imported: u64,
+    },
+    ImportFinished {
+        records: u64,
+        checksum: u64,
+    },
+
+    // Approval events
+    ApprovalForAll {
+        owner: ActorId,
+        operator: ActorId,
+        approved: bool,
+    },
+    ApprovalRevoked {
+        owner: ActorId,
+        revoked_account: ActorId,
+        token_id: TokenId,
+    },
+
+    // Transfer events
+    BatchTransferred {
+        // (from, to, token_id)
+        transfers: Vec<(ActorId, ActorId, TokenId)>,
+    },
+    SafeTransferRejected {
+        from: ActorId,
+        to: ActorId,
+        token_id: TokenId,
+    },
+
+    // Minting events
+    Airdropped {
+        to: ActorId,
+        amount: u128,
+        soulbound: bool,
+    },
+
+    // Lock events
+    Locked {
+        token_id: TokenId,
+        locker: ActorId,
+        until: u64,
+    },
+    Unlocked {
+        token_id: TokenId,
+    },
+
+    // Royalty events
+    DefaultRoyaltyChanged {
+        receiver: ActorId,
+        bps: u16,
+    },
+    TokenRoyaltyChanged {
+        token_id: TokenId,
+        royalty: Option<Royalty>,
+    },
+    SaleTransferred {
+        from: ActorId,
+        to: ActorId,
+        token_id: TokenId,
+        price: u128,
+        royalty: u128,
+    },
+    RoyaltyEnforcedChanged {
+        enforced: bool,
+    },
+
+    // Marketplace events
+    Listed {
+        seller: ActorId,
+        token_id: TokenId,
+        price: u128,
+        expires_at: Option<u64>,
+    },
+    Delisted {
+        token_id: TokenId,
+    },
+    ListingSold {
+        seller: ActorId,
+        buyer: ActorId,
+        token_id: TokenId,
+        price: u128,
+        royalty: u128,
+        fee: u128,
+    },
+    PlatformFeeChanged {
+        bps: u16,
+    },
+    OfferMade {
+        buyer: ActorId,
+        token_id: Option<TokenId>,
+        amount: u128,
+        expires_at: Option<u64>,
+    },
+    OfferCancelled {
+        buyer: ActorId,
+        token_id: Option<TokenId>,
+        refund: u128,
+    },
+    OfferAccepted {
+        seller: ActorId,
+        buyer: ActorId,
+        token_id: TokenId,
+        price: u128,
+        royalty: u128,
+        fee: u128,
+    },
+    AuctionStarted {
+        seller: ActorId,
+        token_id: TokenId,
+        reserve_price: u128,
+        min_increment: u128,
+        ends_at: u64,
+    },
+    BidPlaced {
+        bidder: ActorId,
+        token_id: TokenId,
+        amount: u128,
+        ends_at: u64,
+    },
+    AuctionSettled {
+        seller: ActorId,
+        // `None` if the auction ended without bids
+        winner: Option<ActorId>,
+        token_id: TokenId,
+        price: u128,
+        royalty: u128,
+        fee: u128,
+    },
+    AuctionCancelled {
+        token_id: TokenId,
+    },
+
+    // Rental events
+    UserUpdated {
+        token_id: TokenId,
+        user: ActorId,
+        expires: u64,
+    },
+    RentalTermsChanged {
+        token_id: TokenId,
+        terms: Option<RentalTerms>,
+    },
+    Rented {
+        renter: ActorId,
+        token_id: TokenId,
+        expires: u64,
+        price: u128,
+    },
+
+    // Delegation events
+    Delegated {
+        owner: ActorId,
+        delegate: ActorId,
+        token_ids: Option<Vec<TokenId>>,
+        expires_at: Option<u64>,
+    },
+    DelegateRevoked {
+        owner: ActorId,
+        delegate: ActorId,
+    },
+
+    // Nesting events
+    Nested {
+        token_id: TokenId,
+        parent: TokenParent,
+    },
+    Unnested {
+        token_id: TokenId,
+        parent: TokenParent,
+    },
+    // `msg::source()` doesn't own the parent in its collection or the collection didn't reply
+    UnnestRejected {
+        token_id: TokenId,
+        parent: TokenParent,
+    },
+    // the parent doesn't exist in its collection or the collection didn't reply
+    NestRejected {
+        token_id: TokenId,
+        parent: TokenParent,
+    },
+    NestingCollectionChanged {
+        collection: ActorId,
+        allowed: bool,
+    },
+
+    // Staking events
+    Staked {
+        owner: ActorId,
+        token_ids: Vec<TokenId>,
+    },
+    Unstaked {
+        owner: ActorId,
+        token_ids: Vec<TokenId>,
+    },
+    RewardsClaimed {
+        owner: ActorId,
+        amount: u128,
+    },
+    // the reward token program failed the transfer, the points are kept
+    RewardsClaimRejected {
+        owner: ActorId,
+        amount: u128,
+    },
+    StakingRateChanged {
+        rate: u128,
+    },
+    RewardTokenChanged {
+        reward_token: Option<ActorId>,
+    },
+
+    // Token metadata events
+    MetadataUpdated {
+        token_id: TokenId,
+    },
+    BatchMetadataUpdated {
+        from_token_id: TokenId,
+        to_token_id: TokenId,
+    },
+    MetadataManagerChanged {
+        manager: ActorId,
+    },
+    OwnerMetadataUpdatesChanged {
+        enabled: bool,
+    },
+    AttributesSet {
+        token_ids: Vec<TokenId>,
+    },
+    UriTemplateChanged {
+        suffix: String,
+        padding: u8,
+    },
+    TokenUriChanged {
+        token_id: TokenId,
+        uri: Option<String>,
+    },
+    MetadataFrozen {
+        token_id: TokenId,
+        uri: String,
+    },
+    AllMetadataFrozen,
+
+    // Collection metadata events
+    CollectionUpdated {
+        collection: Collection,
+    },
+}
+
+/// Message sent to the receiving program by `NftAction::SafeTransfer`
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum NftReceiverAction {
+    OnNftReceived {
+        operator: ActorId,
+        from: ActorId,
+        token_id: TokenId,
+        data: Vec<u8>,
+    },
+}
+
+/// Reply expected from the receiving program, any other reply rejects the transfer
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum NftReceiverReply {
+    Accepted,
+    Rejected,
+}
+
+/// Message sent to `Config::reward_token` by `NftAction::ClaimRewards`, laid out as
+/// `FTAction` of the Gear fungible token so that the variant indices match.
+/// Only `Transfer` is sent, the tokens are paid from the balance of the NFT program
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RewardTokenAction {
+    Mint(u128),
+    Burn(u128),
+    Transfer {
+        from: ActorId,
+        to: ActorId,
+        amount: u128,
+    },
+    Approve {
+        to: ActorId,
+        amount: u128,
+    },
+    TotalSupply,
+    BalanceOf(ActorId),
+}
+
+/// Reply of `Config::reward_token`, laid out as `FTEvent` of the Gear fungible token
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RewardTokenEvent {
+    Transfer {
+        from: ActorId,
+        to: ActorId,
+        amount: u128,
+    },
+    Approve {
+        from: ActorId,
+        to: ActorId,
+        amount: u128,
+    },
+    TotalSupply(u128),
+    Balance(u128),
+}
+
+/// Group of actions that can be halted with `NftAction::Pause`.
+///
+/// Only actions that move tokens, commit value or grant new rights pause.
+/// Revocations and withdrawals (`RevokeApproval`, `SetApprovalForAll` with
+/// `approved: false`, `Delist`, `CancelOffer`, `CancelAuction`, `Unlock`,
+/// `Unstake`, `RevokeDelegate`, `ClaimRewards`) stay available so holders can
+/// always back out during an incident.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum PauseScope {
+    /// `Buy`, `Reserve` and `Airdrop`
+    Mint,
+    /// Custody changes and value committed to them: `Transfer`, `BatchTransfer`,
+    /// `SafeTransfer`, `SaleTransfer`, `List`, `BuyListed`, `MakeOffer`, `AcceptOffer`,
+    /// `StartAuction`, `Bid`, `SettleAuction`, `Rent`, `Nest` and `Unnest`
+    Transfer,
+    /// Rights and holds granted over tokens: `Approve`, `SetApprovalForAll`,
+    /// `SetUser`, `SetRentalTerms`, `Delegate`, `Lock` and `Stake`
+    Approve,
+    /// `Burn`
+    Burn,
+    /// Every scope above at once
+    All,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct PauseStatus {
+    pub mint: bool,
+    pub transfer: bool,
+    pub approve: bool,
+    pub burn: bool,
+}
+
+/// Collection-wide part of an exported state, read with `StateQuery::ExportHeader`
+#[derive(Default, Debug, Encode, Decode, TypeInfo)]
+pub struct MigrationHeader {
+    pub owner: ActorId,
+    pub collection: Collection,
+    pub config: Config,
+    pub token_id: TokenId,
+    pub paused: PauseStatus,
+    pub metadata_frozen: bool,
+    // number of records returned by `StateQuery::ExportRecords`
+    pub records: u64,
+    // `migration_checksum` over all of the records
+    pub checksum: u64,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub struct TokenRecord {
+    pub token_id: TokenId,
+    pub owner: ActorId,
+    pub approval: Option<Approval>,
+    pub metadata: TokenMetadata,
+    pub soulbound: bool,
+    pub lock: Option<TokenLock>,
+    pub royalty: Option<Royalty>,
+    pub listing: Option<Listing>,
+    pub user: Option<TokenUser>,
+    pub rental_terms: Option<RentalTerms>,
+    pub parent: Option<TokenParent>,
+    pub attributes: Vec<Attribute>,
+    pub uri: Option<String>,
+    pub metadata_frozen: bool,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub enum MigrationRecord {
+    Token(TokenRecord),
+    Operators {
+        owner: ActorId,
+        operators: Vec<ActorId>,
+    },
+    Delegations {
+        owner: ActorId,
+        delegations: Vec<Delegation>,
+    },
+    Stake {
+        owner: ActorId,
+        token_ids: Vec<TokenId>,
+        // points accrued until the program was frozen
+        points: u128,
+    },
+}
+
+/// FNV-1a hash of the SCALE-encoded record
+pub fn record_checksum(record: &MigrationRecord) -> u64 {
+    record
+        .encode()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Checksum of an exported state. It doesn't depend on the order of the records,
+/// so pages can be imported in any order.
+pub fn migration_checksum<'a>(records: impl IntoIterator<Item = &'a MigrationRecord>) -> u64 {
+    records.into_iter().fold(0, |checksum, record| {
+        checksum.wrapping_add(record_checksum(record))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Approval {
+    pub account: ActorId,
+    // timestamp in seconds after which the approval is treated as absent
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Delegation {
+    pub delegate: ActorId,
+    // `None` covers every token of the owner
+    pub token_ids: Option<Vec<TokenId>>,
+    // timestamp in seconds after which the delegation is treated as absent
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Royalty {
+    pub receiver: ActorId,
+    // share of the sale price in basis points, up to `ROYALTY_BPS_MAX`
+    pub bps: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct RoyaltyInfo {
+    pub receiver: ActorId,
+    pub amount: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Listing {
+    // owner of the token when it was listed, receives the proceeds
+    pub seller: ActorId,
+    // account that listed the token, the listing lapses once it can't manage the token anymore
+    pub lister: ActorId,
+    pub price: u128,
+    // timestamp in seconds after which the listing can't be bought
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Offer {
+    pub buyer: ActorId,
+    // `None` for an offer on any token of the collection
+    pub token_id: Option<TokenId>,
+    // value escrowed by the program
+    pub amount: u128,
+    // timestamp in seconds after which the offer can only be cancelled
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Auction {
+    // owner of the token when the auction started, receives the proceeds
+    pub seller: ActorId,
+    // minimum amount of the first bid
+    pub reserve_price: u128,
+    // minimum amount a bid has to exceed the highest one by
+    pub min_increment: u128,
+    // timestamp in seconds when the auction stops accepting bids
+    pub ends_at: u64,
+    // anti-sniping window in seconds
+    pub extension: u64,
+    pub highest_bid: Option<Bid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Bid {
+    pub bidder: ActorId,
+    // value escrowed by the program
+    pub amount: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct TokenUser {
+    pub user: ActorId,
+    // timestamp in seconds when the user role expires
+    pub expires: u64,
+    // set by a paid `Rent`, can't be replaced before it expires
+    pub rented: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct RentalTerms {
+    pub price_per_second: u128,
+    // longest rental in seconds
+    pub max_duration: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct TokenParent {
+    // `None` for a token of this collection
+    pub collection: Option<ActorId>,
+    pub token_id: TokenId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum RootOwner {
+    Account(ActorId),
+    // token of another collection, its owner is resolved by that collection
+    Token {
+        collection: ActorId,
+        token_id: TokenId,
+    },
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct StakeInfo {
+    pub token_ids: Vec<TokenId>,
+    // points accrued and not claimed yet
+    pub points: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct TokenLock {
+    // the only account able to unlock the token before `until`
+    pub locker: ActorId,
+    // timestamp in seconds when the lock expires
+    pub until: u64,
 }
 
 #[derive(Default, Debug, Encode, Decode, TypeInfo, Clone)]
@@ -192,6 +1067,29 @@ pub struct TokenMetadata {
     pub reference: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, TypeInfo)]
+pub enum AttributeValue {
+    Text(String),
+    Number(u128),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Attribute {
+    // trait type, ex. "Background"
+    pub key: String,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub struct TokenInfo {
+    pub owner: ActorId,
+    pub metadata: TokenMetadata,
+    // the token can't be transferred or approved
+    pub soulbound: bool,
+    // the metadata and URI of the token can't change anymore
+    pub metadata_frozen: bool,
+}
+
 #[derive(Debug, Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
@@ -205,13 +1103,32 @@ pub enum DropspaceNftErr {
 #[derive(Default, Debug, Encode, Decode, TypeInfo)]
 pub struct State {
     pub owner_by_id: Vec<(TokenId, ActorId)>,
-    pub token_approvals: Vec<(TokenId, ActorId)>,
+    pub token_approvals: Vec<(TokenId, Approval)>,
     pub token_metadata_by_id: Vec<(TokenId, TokenMetadata)>,
     pub tokens_for_owner: Vec<(ActorId, Vec<TokenId>)>,
+    pub balances: Vec<(ActorId, u128)>,
+    pub soulbound: Vec<TokenId>,
+    pub operator_approvals: Vec<(ActorId, Vec<ActorId>)>,
+    pub delegations: Vec<(ActorId, Vec<Delegation>)>,
+    pub token_locks: Vec<(TokenId, TokenLock)>,
+    pub token_royalties: Vec<(TokenId, Royalty)>,
+    pub listings: Vec<(TokenId, Listing)>,
+    pub offers: Vec<Offer>,
+    pub auctions: Vec<(TokenId, Auction)>,
+    pub token_users: Vec<(TokenId, TokenUser)>,
+    pub rental_terms: Vec<(TokenId, RentalTerms)>,
+    pub token_parents: Vec<(TokenId, TokenParent)>,
+    pub stakes: Vec<(ActorId, StakeInfo)>,
+    pub token_attributes: Vec<(TokenId, Vec<Attribute>)>,
+    pub token_uris: Vec<(TokenId, String)>,
+    pub frozen_metadata: Vec<TokenId>,
+    pub metadata_frozen: bool,
     pub token_id: TokenId,
     pub owner: ActorId,
     pub collection: Collection,
     pub config: Config,
+    pub paused: PauseStatus,
+    pub frozen: bool,
 }
 
 #[derive(Default, Debug, Encode, Decode, TypeInfo)]
@@ -221,6 +1138,22 @@ pub struct ProgramInfo {
     pub token_id: TokenId,
     pub sale_active: bool,
     pub total_supply: u128,
+    pub paused: PauseStatus,
+    pub frozen: bool,
+    // metadata of all tokens is frozen with `NftAction::FreezeAllMetadata`
+    pub metadata_frozen: bool,
+}
+
+#[derive(Default, Debug, Encode, Decode, TypeInfo)]
+pub struct VersionInfo {
+    // semantic version of the program code
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    // optional capabilities, ex. "pause"
+    pub features: Vec<String>,
+    // implemented interface standards, ex. "gnft-721"
+    pub standards: Vec<String>,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -236,6 +1169,33 @@ pub enum StateQuery {
     OwnerTokens { owner: ActorId },
     SaleActive,
     ProgramInfo,
+    ExportHeader,
+    ExportRecords { offset: u64, limit: u64 },
+    Version,
+    IsApprovedForAll { owner: ActorId, operator: ActorId },
+    BalanceOf { owner: ActorId },
+    BalancesOf { owners: Vec<ActorId> },
+    TokenInfo { token_id: TokenId },
+    TokenLock { token_id: TokenId },
+    RoyaltyInfo { token_id: TokenId, sale_price: u128 },
+    Listings { offset: u64, limit: u64 },
+    BestOffer { token_id: Option<TokenId> },
+    Auction { token_id: TokenId },
+    UserOf { token_id: TokenId },
+    DelegatesOf { owner: ActorId },
+    // `token_id: None` asks for a delegation of every token of `owner`
+    IsDelegate {
+        owner: ActorId,
+        delegate: ActorId,
+        token_id: Option<TokenId>,
+    },
+    Children { token_id: TokenId },
+    RootOwner { token_id: TokenId },
+    StakeInfo { owner: ActorId },
+    TokenAttributes { token_id: TokenId },
+    // number of tokens with each value of the `key` attribute
+    TraitCounts { key: String },
+    TokenUri { token_id: TokenId },
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -246,9 +1206,30 @@ pub enum StateReply {
     Owner(ActorId),
     CurrentTokenId(TokenId),
     OwnerById(Option<ActorId>),
-    TokenApprovals(Option<ActorId>),
+    TokenApprovals(Option<Approval>),
     TokenMetadata(Option<TokenMetadata>),
     OwnerTokens(Option<Vec<TokenId>>),
     SaleActive(bool),
     ProgramInfo(ProgramInfo),
+    ExportHeader(MigrationHeader),
+    ExportRecords(Vec<MigrationRecord>),
+    Version(VersionInfo),
+    IsApprovedForAll(bool),
+    BalanceOf(u128),
+    BalancesOf(Vec<(ActorId, u128)>),
+    TokenInfo(Option<TokenInfo>),
+    TokenLock(Option<TokenLock>),
+    RoyaltyInfo(Option<RoyaltyInfo>),
+    Listings(Vec<(TokenId, Listing)>),
+    BestOffer(Option<Offer>),
+    Auction(Option<Auction>),
+    UserOf(Option<TokenUser>),
+    DelegatesOf(Vec<Delegation>),
+    IsDelegate(bool),
+    Children(Vec<TokenId>),
+    RootOwner(Option<RootOwner>),
+    StakeInfo(StakeInfo),
+    TokenAttributes(Vec<Attribute>),
+    TraitCounts(Vec<(AttributeValue, u128)>),
+    TokenUri(Option<String>),
 }
@@ -12,22 +12,98 @@ use nft_io::*;
 #[derive(Debug, Default)]
 pub struct Nft {
     pub owner_by_id: HashMap<TokenId, ActorId>,
-    pub token_approvals: HashMap<TokenId, ActorId>,
+    pub token_approvals: HashMap<TokenId, Approval>,
     pub token_metadata_by_id: HashMap<TokenId, TokenMetadata>,
     pub tokens_for_owner: HashMap<ActorId, HashSet<TokenId>>,
+    pub balances: HashMap<ActorId, u128>,
+    pub soulbound: HashSet<TokenId>,
+    pub operator_approvals: HashMap<ActorId, HashSet<ActorId>>,
+    pub delegations: HashMap<ActorId, Vec<Delegation>>,
+    pub token_locks: HashMap<TokenId, TokenLock>,
+    pub token_royalties: HashMap<TokenId, Royalty>,
+    pub listings: HashMap<TokenId, Listing>,
+    pub offers: HashMap<(Option<TokenId>, ActorId), Offer>,
+    pub auctions: HashMap<TokenId, Auction>,
+    pub token_users: HashMap<TokenId, TokenUser>,
+    pub rental_terms: HashMap<TokenId, RentalTerms>,
+    pub token_parents: HashMap<TokenId, TokenParent>,
+    pub token_children: HashMap<TokenId, HashSet<TokenId>>,
+    pub stakes: HashMap<ActorId, Stake>,
+    pub token_attributes: HashMap<TokenId, Vec<Attribute>>,
+    pub token_uris: HashMap<TokenId, String>,
+    // tokens frozen one by one, `metadata_frozen` covers all of them
+    pub frozen_metadata: HashSet<TokenId>,
+    pub metadata_frozen: bool,
+    // points accrued per staked token until `staking_updated_at`
+    pub staking_index: u128,
+    pub staking_updated_at: u64,
+    pub pending_transfers: HashSet<TokenId>,
     pub token_id: TokenId,
     pub owner: ActorId,
     pub collection: Collection,
     pub config: Config,
+    pub paused: PauseStatus,
+    pub frozen: bool,
+    pub import: Option<ImportProgress>,
+}
+
+/// State of an import started with `NftAction::BeginImport`
+#[derive(Debug)]
+pub struct ImportProgress {
+    pub header: MigrationHeader,
+    pub records: u64,
+    pub checksum: u64,
+}
+
+/// Tokens staked by an owner
+#[derive(Debug, Default)]
+pub struct Stake {
+    pub token_ids: HashSet<TokenId>,
+    // points accrued until the staking index reached `index`
+    pub points: u128,
+    pub index: u128,
 }
 
 const SALE_TIME_MAX: u64 = u64::MAX;
+/// Number of blocks a `SafeTransfer` receiver has to reply within
+const SAFE_TRANSFER_TIMEOUT: u32 = 100;
+
+/// Capabilities reported by `StateQuery::Version`
+const FEATURES: &[&str] = &[
+    "pause",
+    "migration",
+    "operators",
+    "approval-expiry",
+    "safe-transfer",
+    "soulbound",
+    "locks",
+    "royalties",
+    "sale-transfer",
+    "listings",
+    "offers",
+    "auctions",
+    "rentals",
+    "delegation",
+    "nesting",
+    "staking",
+    "mutable-metadata",
+    "attributes",
+    "uri-template",
+    "metadata-freeze",
+    "collection-metadata",
+];
+/// Interface standards reported by `StateQuery::Version`
+const STANDARDS: &[&str] = &["gnft-721", "erc-2981", "erc-4907", "erc-4906"];
 
 static mut NFT: Option<Nft> = None;
 
 #[no_mangle]
 unsafe extern "C" fn init() {
     let init: InitNft = msg::load().expect("Unable to decode InitNft");
+    check_royalty(&init.config.royalty_receiver, init.config.royalty_bps);
+    check_platform_fee(init.config.platform_fee_bps);
+    check_uri_padding(init.collection.uri_padding);
+    check_collection(&init.collection);
 
     let nft = Nft {
         collection: init.collection,
@@ -46,6 +122,7 @@ impl Nft {
         debug!("Funds: {}", value);
         debug!("Buying Qty: {}", amount);
 
+        self.check_not_paused(PauseScope::Mint);
         self.check_config();
         self.check_zero_address(&source);
 
@@ -132,6 +209,7 @@ impl Nft {
         debug!("Funds: {}", value);
         debug!("Reserving Qty: {}", amount);
 
+        self.check_not_paused(PauseScope::Mint);
         self.check_config();
         self.check_zero_address(&source);
 
@@ -161,16 +239,48 @@ impl Nft {
         NftEvent::Reserved { to: source, amount }
     }
 
+    /// Mint `amount` tokens to `to`, binding them to it if `soulbound` is set
+    fn airdrop(&mut self, to: &ActorId, amount: u128, soulbound: bool) -> NftEvent {
+        self.check_collection_owner();
+
+        debug!("Airdropping Qty: {}", amount);
+
+        self.check_not_paused(PauseScope::Mint);
+        self.check_config();
+        self.check_zero_address(to);
+
+        let total_supply: u128 = self.token_metadata_by_id.len() as u128;
+
+        if amount == 0 {
+            panic!("Amount {} invalid!", amount);
+        }
+
+        if amount > self.config.mint_limit {
+            panic!("Mint limit {} Over!", self.config.mint_limit);
+        }
+
+        if total_supply.saturating_add(amount) > self.config.supply_limit {
+            panic!("Supply limit {} Over!", self.config.supply_limit);
+        }
+
+        for _i in 0..amount {
+            if soulbound {
+                self.soulbound.insert(self.token_id);
+            }
+            self.mint_single(to);
+        }
+
+        NftEvent::Airdropped {
+            to: *to,
+            amount,
+            soulbound,
+        }
+    }
+
     /// Mint a new nft using `TokenMetadata`
     fn mint_single(&mut self, to: &ActorId) -> NftEvent {
         self.owner_by_id.insert(self.token_id, *to);
-
-        self.tokens_for_owner
-            .entry(*to)
-            .and_modify(|tokens| {
-                tokens.insert(self.token_id);
-            })
-            .or_insert_with(|| HashSet::from([self.token_id]));
+        self.add_token_to_owner(to, self.token_id);
 
         let token_metadata = TokenMetadata {
             name: self.token_id.to_string(),
@@ -221,274 +331,2445 @@ impl Nft {
             .get(&token_id)
             .expect("NonFungibleToken: token does not exist");
 
-        self.check_owner(&owner);
+        self.check_not_paused(PauseScope::Burn);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_auctioned(token_id);
+        self.check_not_nested(token_id);
+        self.check_not_staked(&owner, token_id);
+        self.check_not_rented(token_id);
+        if self.token_children.contains_key(&token_id) {
+            panic!("NonFungibleToken: token holds other tokens");
+        }
+        if self.is_operator(&owner, &msg::source()) {
+            // soulbound tokens can only be burnt by their owner
+            self.check_transferable(token_id);
+        } else {
+            self.check_owner(&owner);
+        }
         self.owner_by_id.remove(&token_id);
         self.token_metadata_by_id.remove(&token_id);
-
-        if let Some(tokens) = self.tokens_for_owner.get_mut(&owner) {
-            tokens.remove(&token_id);
-            if tokens.is_empty() {
-                self.tokens_for_owner.remove(&owner);
-            }
-        }
+        self.remove_token_from_owner(&owner, token_id);
         self.token_approvals.remove(&token_id);
+        self.soulbound.remove(&token_id);
+        self.token_royalties.remove(&token_id);
+        self.listings.remove(&token_id);
+        self.token_users.remove(&token_id);
+        self.rental_terms.remove(&token_id);
+        self.drop_token_delegations(&owner, token_id);
+        self.token_attributes.remove(&token_id);
+        self.token_uris.remove(&token_id);
+        self.frozen_metadata.remove(&token_id);
 
         NftEvent::Burnt { token_id }
     }
     ///  Transfer token from `token_id` to address `to`
     fn transfer(&mut self, to: &ActorId, token_id: TokenId) -> NftEvent {
+        let from = self.transfer_token(to, token_id);
+
+        NftEvent::Transferred {
+            from,
+            to: *to,
+            token_id,
+        }
+    }
+    ///  Transfer every token to its paired address. A single failure rejects the whole batch.
+    fn batch_transfer(&mut self, transfers: Vec<(ActorId, TokenId)>) -> NftEvent {
+        if transfers.is_empty() {
+            panic!("NonFungibleToken: nothing to transfer");
+        }
+
+        let transfers = transfers
+            .into_iter()
+            .map(|(to, token_id)| (self.transfer_token(&to, token_id), to, token_id))
+            .collect();
+
+        NftEvent::BatchTransferred { transfers }
+    }
+    /// Moves `token_id` to `to` on behalf of `msg::source()` and returns the previous owner
+    fn transfer_token(&mut self, to: &ActorId, token_id: TokenId) -> ActorId {
+        let owner = self.check_transfer(to, token_id);
+        self.check_royalty_free_transfer(&owner);
+        self.move_token(&owner, to, token_id);
+
+        owner
+    }
+    ///  Sell token `token_id` to address `to` for `price` attached as value,
+    ///  paying the royalty and the rest of the price to the owner.
+    ///  The contract can't verify `price`, it trusts the approved operator to report it.
+    fn sale_transfer(&mut self, to: &ActorId, token_id: TokenId, price: u128) -> NftEvent {
+        let value = msg::value();
+        if price == 0 {
+            panic!("NonFungibleToken: zero sale price");
+        }
+        if price > value {
+            panic!("Funds insufficient!");
+        }
+
+        let owner = self.check_transfer(to, token_id);
+        let royalty = self.pay_sale(token_id, &owner, price, 0);
+        self.move_token(&owner, to, token_id);
+        send_value(&msg::source(), value - price);
+
+        NftEvent::SaleTransferred {
+            from: owner,
+            to: *to,
+            token_id,
+            price,
+            royalty,
+        }
+    }
+    ///  Transfer token to a program that confirms it with `NftReceiverReply::Accepted`,
+    ///  or directly to an account when `to_program` is not set.
+    ///  The token stays with its owner if the program rejects it or doesn't reply in time.
+    async fn safe_transfer(
+        &mut self,
+        to: ActorId,
+        token_id: TokenId,
+        data: Vec<u8>,
+        to_program: bool,
+    ) -> NftEvent {
+        if !to_program {
+            return self.transfer(&to, token_id);
+        }
+
+        let owner = self.check_transfer(&to, token_id);
+        self.check_royalty_free_transfer(&owner);
+        // keep the token in place until the receiver replies
+        self.pending_transfers.insert(token_id);
+
+        let reply = msg::send_for_reply_as::<_, NftReceiverReply>(
+            to,
+            NftReceiverAction::OnNftReceived {
+                operator: msg::source(),
+                from: owner,
+                token_id,
+                data,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message")
+        .up_to(Some(SAFE_TRANSFER_TIMEOUT))
+        .expect("Invalid wait duration")
+        .await;
+
+        self.pending_transfers.remove(&token_id);
+        match reply {
+            Ok(NftReceiverReply::Accepted) => {
+                self.move_token(&owner, &to, token_id);
+
+                NftEvent::Transferred {
+                    from: owner,
+                    to,
+                    token_id,
+                }
+            }
+            _ => NftEvent::SafeTransferRejected {
+                from: owner,
+                to,
+                token_id,
+            },
+        }
+    }
+    /// Checks that `msg::source()` can transfer `token_id` to `to` and returns the token owner
+    fn check_transfer(&mut self, to: &ActorId, token_id: TokenId) -> ActorId {
         let owner = *self
             .owner_by_id
             .get(&token_id)
             .expect("NonFungibleToken: token does not exist");
 
+        self.check_not_paused(PauseScope::Transfer);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_auctioned(token_id);
+        self.check_not_nested(token_id);
+        self.check_not_staked(&owner, token_id);
+        self.check_not_rented(token_id);
+        self.check_transferable(token_id);
         self.can_transfer(token_id, &owner);
         self.check_zero_address(to);
+
+        owner
+    }
+    /// Reassigns `token_id` from `owner` to `to`
+    fn move_token(&mut self, owner: &ActorId, to: &ActorId, token_id: TokenId) {
         // assign new owner
         self.owner_by_id
             .entry(token_id)
             .and_modify(|owner| *owner = *to);
-        // push token to new owner
+        // move token from old owner to new owner
+        self.remove_token_from_owner(owner, token_id);
+        self.add_token_to_owner(to, token_id);
+        // remove approvals if any
+        self.token_approvals.remove(&token_id);
+        // close the listing if any
+        self.listings.remove(&token_id);
+        // the user and the rental terms belong to the previous owner,
+        // only a paid rental of a nested token moving with its parent stays
+        if !self.is_rented(token_id) {
+            self.token_users.remove(&token_id);
+        }
+        self.rental_terms.remove(&token_id);
+        self.drop_token_delegations(owner, token_id);
+        // nested tokens follow their parent
+        if let Some(children) = self.token_children.get(&token_id).cloned() {
+            for child in children {
+                self.move_token(owner, to, child);
+            }
+        }
+    }
+    /// Records `token_id` in the tokens and the balance of `owner`
+    fn add_token_to_owner(&mut self, owner: &ActorId, token_id: TokenId) {
         self.tokens_for_owner
-            .entry(*to)
-            .and_modify(|tokens| {
-                tokens.insert(token_id);
-            })
-            .or_insert_with(|| HashSet::from([token_id]));
-        // remove token from old owner
-        if let Some(tokens) = self.tokens_for_owner.get_mut(&owner) {
+            .entry(*owner)
+            .or_default()
+            .insert(token_id);
+        *self.balances.entry(*owner).or_default() += 1;
+    }
+    /// Removes `token_id` from the tokens and the balance of `owner`
+    fn remove_token_from_owner(&mut self, owner: &ActorId, token_id: TokenId) {
+        if let Some(tokens) = self.tokens_for_owner.get_mut(owner) {
             tokens.remove(&token_id);
             if tokens.is_empty() {
-                self.tokens_for_owner.remove(&owner);
+                self.tokens_for_owner.remove(owner);
             }
         }
-        // remove approvals if any
-        self.token_approvals.remove(&token_id);
+        if let Some(balance) = self.balances.get_mut(owner) {
+            *balance -= 1;
+            if *balance == 0 {
+                self.balances.remove(owner);
+            }
+        }
+    }
+    /// Get the number of tokens owned by `owner`
+    fn balance_of(&self, owner: &ActorId) -> u128 {
+        self.balances.get(owner).cloned().unwrap_or_default()
+    }
+    /// Get the metadata of `token_id` with its reference pointing to the token URI
+    fn token_metadata(&self, token_id: TokenId) -> Option<TokenMetadata> {
+        let token_metadata = self.token_metadata_by_id.get(&token_id)?;
+
+        Some(TokenMetadata {
+            reference: self.token_uri(token_id)?,
+            ..token_metadata.clone()
+        })
+    }
+    /// Get the URI override of `token_id` or its URI built from the collection template
+    fn token_uri(&self, token_id: TokenId) -> Option<String> {
+        if !self.owner_by_id.contains_key(&token_id) {
+            return None;
+        }
+        if let Some(uri) = self.token_uris.get(&token_id) {
+            return Some(uri.clone());
+        }
 
-        NftEvent::Transferred {
-            from: owner,
-            to: *to,
+        Some(format!(
+            "{}{:0padding$}{}",
+            self.collection.base_uri,
             token_id,
-        }
+            self.collection.uri_suffix,
+            padding = usize::from(self.collection.uri_padding)
+        ))
     }
-    ///  Approve token from `token_id` to address `to`
-    fn approve(&mut self, to: &ActorId, token_id: TokenId) -> NftEvent {
-        let owner = self
+    ///  Approve token from `token_id` to address `to` until `expires_at`,
+    ///  replacing the previous approval if any
+    fn approve(&mut self, to: &ActorId, token_id: TokenId, expires_at: Option<u64>) -> NftEvent {
+        let owner = *self
             .owner_by_id
             .get(&token_id)
             .expect("NonFungibleToken: token does not exist");
-        self.check_owner(owner);
+        self.check_not_paused(PauseScope::Approve);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_transferable(token_id);
+        self.check_owner(&owner);
         self.check_zero_address(to);
-        self.check_approve(&token_id);
-        self.token_approvals.insert(token_id, *to);
+        if is_expired(expires_at) {
+            panic!("NonFungibleToken: approval expires in the past");
+        }
+        self.token_approvals.insert(
+            token_id,
+            Approval {
+                account: *to,
+                expires_at,
+            },
+        );
 
         NftEvent::Approved {
-            owner: *owner,
+            owner,
             approved_account: *to,
             token_id,
+            expires_at,
         }
     }
-    /// Get `ActorId` of the nft owner with `token_id`
-    fn owner(&self, token_id: TokenId) -> NftEvent {
+    ///  Remove the approval issued for `token_id`
+    fn revoke_approval(&mut self, token_id: TokenId) -> NftEvent {
         let owner = self
             .owner_by_id
             .get(&token_id)
             .expect("NonFungibleToken: token does not exist");
+        self.check_owner(owner);
+        let approval = self
+            .token_approvals
+            .remove(&token_id)
+            .expect("NonFungibleToken: token is not approved");
 
-        NftEvent::Owner {
+        NftEvent::ApprovalRevoked {
             owner: *owner,
+            revoked_account: approval.account,
             token_id,
         }
     }
-    /// Get confirmation about approval to address `to` and `token_id`
-    fn is_approved_to(&self, to: &ActorId, token_id: TokenId) -> NftEvent {
-        if !self.owner_by_id.contains_key(&token_id) {
-            panic!("Token does not exist")
-        }
-        self.token_approvals.get(&token_id).map_or_else(
-            || NftEvent::CheckIfApproved {
-                to: *to,
-                token_id,
-                approved: false,
-            },
-            |approval_id| NftEvent::CheckIfApproved {
-                to: *to,
-                token_id,
-                approved: *approval_id == *to,
-            },
-        )
-    }
+    /// Allow or forbid `operator` to manage all tokens of `msg::source()`
+    fn set_approval_for_all(&mut self, operator: &ActorId, approved: bool) -> NftEvent {
+        let owner = msg::source();
 
-    /// Checking the configuration with current contract data
-    fn check_config(&self) {
-        if self.config.supply_limit <= self.token_metadata_by_id.len() as u128 {
-            panic!(
-                "Mint impossible because max minting count {} limit exceeded",
-                self.config.supply_limit
-            );
+        if approved {
+            self.check_not_paused(PauseScope::Approve);
+        }
+        if !self.config.transferable {
+            panic!("NonFungibleToken: collection is soulbound");
+        }
+        self.check_zero_address(operator);
+        if *operator == owner {
+            panic!("NonFungibleToken: approval to the caller");
         }
-    }
 
-    /// Check for ZERO_ID address
-    fn check_zero_address(&self, account: &ActorId) {
-        if account == &ZERO_ID {
-            panic!("NonFungibleToken: zero address");
+        if approved {
+            self.operator_approvals
+                .entry(owner)
+                .or_default()
+                .insert(*operator);
+        } else if let Some(operators) = self.operator_approvals.get_mut(&owner) {
+            operators.remove(operator);
+            if operators.is_empty() {
+                self.operator_approvals.remove(&owner);
+            }
         }
-    }
 
-    /// Checks that `msg::source()` is the owner of the token with indicated `token_id`
-    fn check_owner(&self, owner: &ActorId) {
-        if owner != &msg::source() {
-            panic!("NonFungibleToken: access denied");
+        NftEvent::ApprovalForAll {
+            owner,
+            operator: *operator,
+            approved,
         }
     }
+    /// Freeze `token_id` in place until `until`, only `locker` can lift the lock earlier.
+    /// Only the owner grants a lock, approvals and operators can't outlive themselves through it
+    fn lock(&mut self, token_id: TokenId, until: u64, locker: &ActorId) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
 
-    /// Checks that `msg::source()` is the owner of the collection
-    fn check_collection_owner(&self) {
-        if self.owner != msg::source() {
-            panic!("NonFungibleToken: not authorized");
+        self.check_not_paused(PauseScope::Approve);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_auctioned(token_id);
+        // a lock can't hold a token that moves with its parent
+        self.check_not_nested(token_id);
+        self.check_owner(&owner);
+        self.check_zero_address(locker);
+        if until <= current_timestamp() {
+            panic!("NonFungibleToken: lock expires in the past");
         }
-    }
+        self.token_locks.insert(
+            token_id,
+            TokenLock {
+                locker: *locker,
+                until,
+            },
+        );
 
-    /// Checks that `msg::source()` is allowed to manage the token with indicated `token_id`
-    fn can_transfer(&self, token_id: TokenId, owner: &ActorId) {
-        if let Some(approved_accounts) = self.token_approvals.get(&token_id) {
-            if approved_accounts == &msg::source() {
-                return;
-            }
+        NftEvent::Locked {
+            token_id,
+            locker: *locker,
+            until,
         }
-        self.check_owner(owner);
     }
-    /// Check the existence of a approve
-    fn check_approve(&self, token_id: &TokenId) {
-        if self.token_approvals.contains_key(token_id) {
-            panic!("Approve has already been issued");
+    /// Lift the lock of `token_id`
+    fn unlock(&mut self, token_id: TokenId) -> NftEvent {
+        let lock = self
+            .active_lock(token_id)
+            .expect("NonFungibleToken: token is not locked");
+        if lock.locker != msg::source() {
+            panic!("NonFungibleToken: not the locker");
         }
+        self.token_locks.remove(&token_id);
+
+        NftEvent::Unlocked { token_id }
     }
+    /// List `token_id` for sale at `price` until `expires_at`, replacing the previous listing if any
+    fn list(&mut self, token_id: TokenId, price: u128, expires_at: Option<u64>) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
 
-    /// Set collection's name
-    fn set_name(&mut self, name: &String) -> NftEvent {
-        self.check_collection_owner();
-        self.collection.name = name.to_string();
+        self.check_not_paused(PauseScope::Transfer);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_auctioned(token_id);
+        self.check_not_nested(token_id);
+        self.check_not_staked(&owner, token_id);
+        self.check_not_rented(token_id);
+        self.check_transferable(token_id);
+        self.can_transfer(token_id, &owner);
+        if price == 0 {
+            panic!("NonFungibleToken: zero price");
+        }
+        if is_expired(expires_at) {
+            panic!("NonFungibleToken: listing expires in the past");
+        }
+        self.listings.insert(
+            token_id,
+            Listing {
+                seller: owner,
+                lister: msg::source(),
+                price,
+                expires_at,
+            },
+        );
 
-        NftEvent::NameChanged {
-            name: name.to_string(),
+        NftEvent::Listed {
+            seller: owner,
+            token_id,
+            price,
+            expires_at,
         }
     }
+    /// Close the listing of `token_id`
+    fn delist(&mut self, token_id: TokenId) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
 
-    /// Set collection's description
-    fn set_description(&mut self, description: &String) -> NftEvent {
-        self.check_collection_owner();
-        self.collection.description = description.to_string();
-
-        NftEvent::DescriptionChanged {
-            description: description.to_string(),
+        self.can_transfer(token_id, &owner);
+        if self.listings.remove(&token_id).is_none() {
+            panic!("NonFungibleToken: token is not listed");
         }
-    }
 
-    /// Set collection's symbol
-    fn set_symbol(&mut self, symbol: &String) -> NftEvent {
-        self.check_collection_owner();
-        self.collection.symbol = symbol.to_string();
+        NftEvent::Delisted { token_id }
+    }
+    /// Buy listed `token_id` paying its price attached as value
+    fn buy_listed(&mut self, token_id: TokenId) -> NftEvent {
+        let buyer = msg::source();
+        let value = msg::value();
+        let listing = self
+            .active_listing(token_id)
+            .expect("NonFungibleToken: token is not listed");
 
-        NftEvent::SymbolChanged {
-            symbol: symbol.to_string(),
+        if listing.price > value {
+            panic!("Funds insufficient!");
+        }
+        if listing.seller == buyer {
+            panic!("NonFungibleToken: seller can't buy own listing");
+        }
+        self.check_not_paused(PauseScope::Transfer);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_rented(token_id);
+        self.check_transferable(token_id);
+
+        let fee = bps_of(listing.price, self.config.platform_fee_bps);
+        let royalty = self.pay_sale(token_id, &listing.seller, listing.price, fee);
+        self.move_token(&listing.seller, &buyer, token_id);
+        send_value(&buyer, value - listing.price);
+
+        NftEvent::ListingSold {
+            seller: listing.seller,
+            buyer,
+            token_id,
+            price: listing.price,
+            royalty,
+            fee,
         }
     }
+    /// Get the listing of `token_id` unless it has expired or its lister can't manage the token
+    fn active_listing(&self, token_id: TokenId) -> Option<Listing> {
+        self.listings
+            .get(&token_id)
+            .filter(|listing| !is_expired(listing.expires_at))
+            .filter(|listing| self.is_authorized(token_id, &listing.seller, &listing.lister))
+            .cloned()
+    }
+    /// Active listings ordered by token id
+    fn listings(&self, offset: u64, limit: u64) -> Vec<(TokenId, Listing)> {
+        let mut token_ids: Vec<TokenId> = self.listings.keys().cloned().collect();
+        token_ids.sort_unstable();
 
-    /// Set collection's base_uri
-    fn set_base_uri(&mut self, base_uri: &String) -> NftEvent {
-        self.check_collection_owner();
-        self.collection.base_uri = base_uri.to_string();
-
-        NftEvent::BaseUriChanged {
-            base_uri: base_uri.to_string(),
-        }
+        token_ids
+            .into_iter()
+            .filter_map(|token_id| Some((token_id, self.active_listing(token_id)?)))
+            .skip(offset as usize)
+            .take(limit.min(LISTINGS_PAGE_LIMIT) as usize)
+            .collect()
     }
+    /// Escrow the attached value as an offer on `token_id` or on any token if it's `None`,
+    /// replacing and refunding the previous offer of the buyer if any
+    fn make_offer(&mut self, token_id: Option<TokenId>, expires_at: Option<u64>) -> NftEvent {
+        let buyer = msg::source();
+        let amount = msg::value();
+
+        self.check_not_paused(PauseScope::Transfer);
+        if amount == 0 {
+            panic!("NonFungibleToken: zero offer");
+        }
+        if is_expired(expires_at) {
+            panic!("NonFungibleToken: offer expires in the past");
+        }
+        match token_id {
+            Some(token_id) => {
+                let owner = self
+                    .owner_by_id
+                    .get(&token_id)
+                    .expect("NonFungibleToken: token does not exist");
+                if *owner == buyer {
+                    panic!("NonFungibleToken: owner can't make an offer");
+                }
+                self.check_transferable(token_id);
+            }
+            None => {
+                if !self.config.transferable {
+                    panic!("NonFungibleToken: collection is soulbound");
+                }
+            }
+        }
 
-    // Set withdraw_address.
-    fn set_withdraw_wallet(&mut self, withdraw_wallet: &ActorId) -> NftEvent {
-        self.check_collection_owner();
-        self.config.withdraw_wallet = *withdraw_wallet;
+        let previous = self.offers.insert(
+            (token_id, buyer),
+            Offer {
+                buyer,
+                token_id,
+                amount,
+                expires_at,
+            },
+        );
+        if let Some(previous) = previous {
+            send_value(&buyer, previous.amount);
+        }
 
-        NftEvent::WithdrawWalletChanged {
-            withdraw_wallet: *withdraw_wallet,
+        NftEvent::OfferMade {
+            buyer,
+            token_id,
+            amount,
+            expires_at,
+        }
+    }
+    /// Withdraw the offer of `msg::source()` and refund its escrowed value
+    fn cancel_offer(&mut self, token_id: Option<TokenId>) -> NftEvent {
+        let buyer = msg::source();
+        let offer = self
+            .offers
+            .remove(&(token_id, buyer))
+            .expect("NonFungibleToken: offer does not exist");
+        send_value(&buyer, offer.amount);
+
+        NftEvent::OfferCancelled {
+            buyer,
+            token_id,
+            refund: offer.amount,
         }
     }
+    /// Sell `token_id` to `buyer` for its escrowed offer
+    fn accept_offer(
+        &mut self,
+        token_id: TokenId,
+        buyer: &ActorId,
+        collection_offer: bool,
+    ) -> NftEvent {
+        let key = if collection_offer {
+            (None, *buyer)
+        } else {
+            (Some(token_id), *buyer)
+        };
+        let offer = *self
+            .offers
+            .get(&key)
+            .filter(|offer| !is_expired(offer.expires_at))
+            .expect("NonFungibleToken: offer does not exist");
+
+        let owner = self.check_transfer(buyer, token_id);
+        if owner == *buyer {
+            panic!("NonFungibleToken: buyer already owns the token");
+        }
+        self.offers.remove(&key);
 
-    /// Set supply limit.
-    fn set_supply_limit(&mut self, supply_limit: u128) -> NftEvent {
-        self.check_collection_owner();
-        self.config.supply_limit = supply_limit;
+        let fee = bps_of(offer.amount, self.config.platform_fee_bps);
+        let royalty = self.pay_sale(token_id, &owner, offer.amount, fee);
+        self.move_token(&owner, buyer, token_id);
 
-        NftEvent::SupplyLimitChanged { supply_limit }
+        NftEvent::OfferAccepted {
+            seller: owner,
+            buyer: *buyer,
+            token_id,
+            price: offer.amount,
+            royalty,
+            fee,
+        }
+    }
+    /// Get the highest active offer on `token_id` or on any token if it's `None`
+    fn best_offer(&self, token_id: Option<TokenId>) -> Option<Offer> {
+        self.offers
+            .values()
+            .filter(|offer| offer.token_id == token_id && !is_expired(offer.expires_at))
+            .max_by_key(|offer| (offer.amount, offer.buyer))
+            .cloned()
+    }
+    /// Auction `token_id` until `ends_at`, the token can't be moved until the auction is settled
+    fn start_auction(
+        &mut self,
+        token_id: TokenId,
+        reserve_price: u128,
+        min_increment: u128,
+        ends_at: u64,
+        extension: u64,
+    ) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+
+        self.check_not_paused(PauseScope::Transfer);
+        self.check_not_pending(token_id);
+        self.check_not_locked(token_id);
+        self.check_not_auctioned(token_id);
+        self.check_not_nested(token_id);
+        self.check_not_staked(&owner, token_id);
+        self.check_not_rented(token_id);
+        self.check_transferable(token_id);
+        self.check_owner(&owner);
+        if ends_at <= current_timestamp() {
+            panic!("NonFungibleToken: auction ends in the past");
+        }
+        // the auction replaces the listing if any
+        self.listings.remove(&token_id);
+        self.auctions.insert(
+            token_id,
+            Auction {
+                seller: owner,
+                reserve_price,
+                min_increment,
+                ends_at,
+                extension,
+                highest_bid: None,
+            },
+        );
+
+        NftEvent::AuctionStarted {
+            seller: owner,
+            token_id,
+            reserve_price,
+            min_increment,
+            ends_at,
+        }
+    }
+    /// Bid the attached value on the auctioned `token_id`, refunding the outbid bidder
+    fn bid(&mut self, token_id: TokenId) -> NftEvent {
+        let bidder = msg::source();
+        let amount = msg::value();
+        let now = current_timestamp();
+        self.check_not_paused(PauseScope::Transfer);
+        let auction = self
+            .auctions
+            .get_mut(&token_id)
+            .expect("NonFungibleToken: token is not auctioned");
+
+        if now >= auction.ends_at {
+            panic!("NonFungibleToken: auction has ended");
+        }
+        if bidder == auction.seller {
+            panic!("NonFungibleToken: seller can't bid");
+        }
+        let min_bid = match auction.highest_bid {
+            Some(bid) => bid.amount.saturating_add(auction.min_increment.max(1)),
+            None => auction.reserve_price.max(1),
+        };
+        if amount < min_bid {
+            panic!("NonFungibleToken: bid is below {}", min_bid);
+        }
+
+        let outbid = auction.highest_bid.replace(Bid { bidder, amount });
+        if auction.ends_at - now < auction.extension {
+            auction.ends_at = now.saturating_add(auction.extension);
+        }
+        let ends_at = auction.ends_at;
+        if let Some(outbid) = outbid {
+            send_value(&outbid.bidder, outbid.amount);
+        }
+
+        NftEvent::BidPlaced {
+            bidder,
+            token_id,
+            amount,
+            ends_at,
+        }
+    }
+    /// Transfer the ended auction of `token_id` to the highest bidder and pay out the bid
+    fn settle_auction(&mut self, token_id: TokenId) -> NftEvent {
+        let auction = *self
+            .auctions
+            .get(&token_id)
+            .expect("NonFungibleToken: token is not auctioned");
+
+        if current_timestamp() < auction.ends_at {
+            panic!("NonFungibleToken: auction is live");
+        }
+        self.check_not_paused(PauseScope::Transfer);
+        self.auctions.remove(&token_id);
+
+        let (winner, price, royalty, fee) = match auction.highest_bid {
+            Some(bid) => {
+                let fee = bps_of(bid.amount, self.config.platform_fee_bps);
+                let royalty = self.pay_sale(token_id, &auction.seller, bid.amount, fee);
+                self.move_token(&auction.seller, &bid.bidder, token_id);
+
+                (Some(bid.bidder), bid.amount, royalty, fee)
+            }
+            None => (None, 0, 0, 0),
+        };
+
+        NftEvent::AuctionSettled {
+            seller: auction.seller,
+            winner,
+            token_id,
+            price,
+            royalty,
+            fee,
+        }
+    }
+    /// Stop the auction of `token_id` before it has any bids
+    fn cancel_auction(&mut self, token_id: TokenId) -> NftEvent {
+        let auction = self
+            .auctions
+            .get(&token_id)
+            .expect("NonFungibleToken: token is not auctioned");
+
+        self.check_owner(&auction.seller);
+        if auction.highest_bid.is_some() {
+            panic!("NonFungibleToken: auction has bids");
+        }
+        self.auctions.remove(&token_id);
+
+        NftEvent::AuctionCancelled { token_id }
+    }
+    /// Let `user` use `token_id` until `expires`, replacing the previous user if any
+    fn set_user(&mut self, token_id: TokenId, user: &ActorId, expires: u64) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+
+        self.check_not_paused(PauseScope::Approve);
+        self.can_transfer(token_id, &owner);
+        self.check_not_rented(token_id);
+        if *user == ZERO_ID {
+            self.token_users.remove(&token_id);
+        } else {
+            if is_expired(Some(expires)) {
+                panic!("NonFungibleToken: user expires in the past");
+            }
+            self.token_users.insert(
+                token_id,
+                TokenUser {
+                    user: *user,
+                    expires,
+                    rented: false,
+                },
+            );
+        }
+
+        NftEvent::UserUpdated {
+            token_id,
+            user: *user,
+            expires,
+        }
+    }
+    /// Offer `token_id` for paid rentals, `None` stops renting it out
+    fn set_rental_terms(&mut self, token_id: TokenId, terms: Option<RentalTerms>) -> NftEvent {
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+
+        self.check_not_paused(PauseScope::Approve);
+        self.check_owner(&owner);
+        match terms {
+            Some(terms) => {
+                if terms.max_duration == 0 {
+                    panic!("NonFungibleToken: zero rental duration");
+                }
+                self.rental_terms.insert(token_id, terms);
+            }
+            None => {
+                self.rental_terms.remove(&token_id);
+            }
+        }
+
+        NftEvent::RentalTermsChanged { token_id, terms }
+    }
+    /// Rent `token_id` for `duration` seconds, paying the owner with the attached value
+    fn rent(&mut self, token_id: TokenId, duration: u64) -> NftEvent {
+        let renter = msg::source();
+        let value = msg::value();
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+        let terms = *self
+            .rental_terms
+            .get(&token_id)
+            .expect("NonFungibleToken: token is not for rent");
+
+        self.check_not_paused(PauseScope::Transfer);
+        // the auction winner gets the token without `check_transfer`
+        self.check_not_auctioned(token_id);
+        if renter == owner {
+            panic!("NonFungibleToken: owner can't rent its own token");
+        }
+        if duration == 0 || duration > terms.max_duration {
+            panic!("NonFungibleToken: rental duration {} invalid", duration);
+        }
+        if self.active_user(token_id).is_some() {
+            panic!("NonFungibleToken: token is in use");
+        }
+        let price = terms.price_per_second.saturating_mul(u128::from(duration));
+        if price > value {
+            panic!("Funds insufficient!");
+        }
+
+        let expires = current_timestamp().saturating_add(duration);
+        self.token_users.insert(
+            token_id,
+            TokenUser {
+                user: renter,
+                expires,
+                rented: true,
+            },
+        );
+        send_value(&owner, price);
+        send_value(&renter, value - price);
+
+        NftEvent::Rented {
+            renter,
+            token_id,
+            expires,
+            price,
+        }
+    }
+    /// Get the user of `token_id` unless its role has expired
+    fn active_user(&self, token_id: TokenId) -> Option<TokenUser> {
+        self.token_users
+            .get(&token_id)
+            .filter(|user| !is_expired(Some(user.expires)))
+            .cloned()
+    }
+    /// Check whether `token_id` is in a paid rental that hasn't expired
+    fn is_rented(&self, token_id: TokenId) -> bool {
+        self.active_user(token_id).is_some_and(|user| user.rented)
+    }
+    /// Checks that the owner can't void a paid rental of `token_id` by moving or burning it
+    fn check_not_rented(&self, token_id: TokenId) {
+        if self.is_rented(token_id) {
+            panic!("NonFungibleToken: token is rented");
+        }
+    }
+    /// Let `delegate` act as the holder of `token_ids` or of every token of `msg::source()`,
+    /// replacing the previous delegation to `delegate` if any
+    fn delegate(
+        &mut self,
+        delegate: &ActorId,
+        token_ids: Option<Vec<TokenId>>,
+        expires_at: Option<u64>,
+    ) -> NftEvent {
+        let owner = msg::source();
+
+        self.check_not_paused(PauseScope::Approve);
+        self.check_zero_address(delegate);
+        if *delegate == owner {
+            panic!("NonFungibleToken: self delegation");
+        }
+        if is_expired(expires_at) {
+            panic!("NonFungibleToken: delegation expires in the past");
+        }
+        if let Some(token_ids) = &token_ids {
+            if token_ids.is_empty() {
+                panic!("NonFungibleToken: nothing to delegate");
+            }
+            for token_id in token_ids {
+                let token_owner = self
+                    .owner_by_id
+                    .get(token_id)
+                    .expect("NonFungibleToken: token does not exist");
+                self.check_owner(token_owner);
+            }
+        }
+
+        let delegations = self.delegations.entry(owner).or_default();
+        delegations.retain(|delegation| {
+            delegation.delegate != *delegate && !is_expired(delegation.expires_at)
+        });
+        delegations.push(Delegation {
+            delegate: *delegate,
+            token_ids: token_ids.clone(),
+            expires_at,
+        });
+
+        NftEvent::Delegated {
+            owner,
+            delegate: *delegate,
+            token_ids,
+            expires_at,
+        }
+    }
+    /// Remove the delegation of `msg::source()` to `delegate`
+    fn revoke_delegate(&mut self, delegate: &ActorId) -> NftEvent {
+        let owner = msg::source();
+        let delegations = self
+            .delegations
+            .get_mut(&owner)
+            .expect("NonFungibleToken: delegation does not exist");
+
+        let count = delegations.len();
+        delegations.retain(|delegation| delegation.delegate != *delegate);
+        if delegations.len() == count {
+            panic!("NonFungibleToken: delegation does not exist");
+        }
+        if delegations.is_empty() {
+            self.delegations.remove(&owner);
+        }
+
+        NftEvent::DelegateRevoked {
+            owner,
+            delegate: *delegate,
+        }
+    }
+    /// Remove `token_id` from the delegations of its previous `owner`,
+    /// dropping the delegations left without tokens
+    fn drop_token_delegations(&mut self, owner: &ActorId, token_id: TokenId) {
+        let Some(delegations) = self.delegations.get_mut(owner) else {
+            return;
+        };
+        for delegation in delegations.iter_mut() {
+            if let Some(token_ids) = &mut delegation.token_ids {
+                token_ids.retain(|id| *id != token_id);
+            }
+        }
+        delegations.retain(
+            |delegation| !matches!(&delegation.token_ids, Some(token_ids) if token_ids.is_empty()),
+        );
+        if delegations.is_empty() {
+            self.delegations.remove(owner);
+        }
+    }
+    /// Get the unexpired delegations of `owner`
+    fn delegates_of(&self, owner: &ActorId) -> Vec<Delegation> {
+        self.delegations
+            .get(owner)
+            .map(|delegations| {
+                delegations
+                    .iter()
+                    .filter(|delegation| !is_expired(delegation.expires_at))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// Checks that `delegate` acts for `owner` on `token_id` or on every token if it's `None`
+    fn is_delegate(&self, owner: &ActorId, delegate: &ActorId, token_id: Option<TokenId>) -> bool {
+        if token_id.is_some_and(|token_id| self.owner_by_id.get(&token_id) != Some(owner)) {
+            return false;
+        }
+        self.delegations.get(owner).is_some_and(|delegations| {
+            delegations.iter().any(|delegation| {
+                delegation.delegate == *delegate
+                    && !is_expired(delegation.expires_at)
+                    && match (&delegation.token_ids, token_id) {
+                        (None, _) => true,
+                        (Some(token_ids), Some(token_id)) => token_ids.contains(&token_id),
+                        (Some(_), None) => false,
+                    }
+            })
+        })
+    }
+    /// Put `token_id` into the `parent` token, the owner of the parent becomes its owner.
+    /// A parent from another collection is looked up in that collection first.
+    async fn nest(&mut self, token_id: TokenId, parent: TokenParent) -> NftEvent {
+        let Some(collection) = parent.collection else {
+            let parent_owner = *self
+                .owner_by_id
+                .get(&parent.token_id)
+                .expect("NonFungibleToken: parent token does not exist");
+            let mut ancestor = Some(parent.token_id);
+            while let Some(ancestor_id) = ancestor {
+                if ancestor_id == token_id {
+                    panic!("NonFungibleToken: token can't be nested into itself");
+                }
+                ancestor = self
+                    .token_parents
+                    .get(&ancestor_id)
+                    .filter(|parent| parent.collection.is_none())
+                    .map(|parent| parent.token_id);
+            }
+
+            let owner = self.check_transfer(&parent_owner, token_id);
+            self.check_royalty_free_transfer(&owner);
+            self.move_token(&owner, &parent_owner, token_id);
+            self.token_parents.insert(token_id, parent);
+            self.token_children
+                .entry(parent.token_id)
+                .or_default()
+                .insert(token_id);
+
+            return NftEvent::Nested { token_id, parent };
+        };
+
+        if !self.config.nesting_collections.contains(&collection) {
+            panic!("NonFungibleToken: collection can't hold tokens");
+        }
+        let owner = self.check_transfer(&collection, token_id);
+        self.check_royalty_free_transfer(&owner);
+        // keep the token in place until the parent collection confirms the parent exists,
+        // a token nested into a missing parent could never be unnested
+        self.pending_transfers.insert(token_id);
+
+        let reply = msg::send_for_reply_as::<_, NftEvent>(
+            collection,
+            NftAction::GetOwner {
+                token_id: parent.token_id,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message")
+        .up_to(Some(SAFE_TRANSFER_TIMEOUT))
+        .expect("Invalid wait duration")
+        .await;
+
+        self.pending_transfers.remove(&token_id);
+        match reply {
+            Ok(NftEvent::Owner {
+                token_id: parent_id,
+                ..
+            }) if parent_id == parent.token_id => {
+                self.move_token(&owner, &collection, token_id);
+                self.token_parents.insert(token_id, parent);
+
+                NftEvent::Nested { token_id, parent }
+            }
+            _ => NftEvent::NestRejected { token_id, parent },
+        }
+    }
+    /// Take `token_id` out of its parent on behalf of the root owner.
+    /// The owner of a parent from another collection is asked from that collection.
+    async fn unnest(&mut self, token_id: TokenId) -> NftEvent {
+        let source = msg::source();
+        let parent = *self
+            .token_parents
+            .get(&token_id)
+            .expect("NonFungibleToken: token is not nested");
+
+        let Some(collection) = parent.collection else {
+            if self.root_owner(token_id) != Some(RootOwner::Account(source)) {
+                panic!("NonFungibleToken: access denied");
+            }
+            self.check_not_paused(PauseScope::Transfer);
+            self.check_bundle_not_committed(token_id);
+            self.detach_token(token_id, parent);
+
+            return NftEvent::Unnested { token_id, parent };
+        };
+
+        self.check_not_paused(PauseScope::Transfer);
+        self.check_not_pending(token_id);
+        // keep the token in place until the parent collection replies
+        self.pending_transfers.insert(token_id);
+
+        let reply = msg::send_for_reply_as::<_, NftEvent>(
+            collection,
+            NftAction::GetOwner {
+                token_id: parent.token_id,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message")
+        .up_to(Some(SAFE_TRANSFER_TIMEOUT))
+        .expect("Invalid wait duration")
+        .await;
+
+        self.pending_transfers.remove(&token_id);
+        match reply {
+            Ok(NftEvent::Owner { owner, .. }) if owner == source => {
+                self.detach_token(token_id, parent);
+                self.move_token(&collection, &source, token_id);
+
+                NftEvent::Unnested { token_id, parent }
+            }
+            _ => NftEvent::UnnestRejected { token_id, parent },
+        }
+    }
+    /// Checks that no parent of `token_id` is listed, auctioned or staked along with it
+    fn check_bundle_not_committed(&self, token_id: TokenId) {
+        let mut ancestor = self.token_parents.get(&token_id).copied();
+        while let Some(TokenParent {
+            collection: None,
+            token_id: ancestor_id,
+        }) = ancestor
+        {
+            self.check_not_auctioned(ancestor_id);
+            if self.active_listing(ancestor_id).is_some() {
+                panic!("NonFungibleToken: token {} is listed", ancestor_id);
+            }
+            self.check_not_staked(&self.owner_by_id[&ancestor_id], ancestor_id);
+            ancestor = self.token_parents.get(&ancestor_id).copied();
+        }
+    }
+    /// Removes the link between `token_id` and its `parent`
+    fn detach_token(&mut self, token_id: TokenId, parent: TokenParent) {
+        self.token_parents.remove(&token_id);
+        if parent.collection.is_none() {
+            if let Some(children) = self.token_children.get_mut(&parent.token_id) {
+                children.remove(&token_id);
+                if children.is_empty() {
+                    self.token_children.remove(&parent.token_id);
+                }
+            }
+        }
+    }
+    /// Get the account or the token of another collection at the top of the parents of `token_id`
+    fn root_owner(&self, token_id: TokenId) -> Option<RootOwner> {
+        let owner = *self.owner_by_id.get(&token_id)?;
+        let mut token_id = token_id;
+        while let Some(parent) = self.token_parents.get(&token_id) {
+            match parent.collection {
+                None => token_id = parent.token_id,
+                Some(collection) => {
+                    return Some(RootOwner::Token {
+                        collection,
+                        token_id: parent.token_id,
+                    })
+                }
+            }
+        }
+
+        Some(RootOwner::Account(owner))
+    }
+    /// Get the tokens of this collection nested into `token_id`
+    fn children(&self, token_id: TokenId) -> Vec<TokenId> {
+        let mut children: Vec<TokenId> = self
+            .token_children
+            .get(&token_id)
+            .map(|children| children.iter().cloned().collect())
+            .unwrap_or_default();
+        children.sort_unstable();
+
+        children
+    }
+    /// Stake `token_ids` of `msg::source()` in place to accrue reward points
+    fn stake(&mut self, token_ids: Vec<TokenId>) -> NftEvent {
+        let owner = msg::source();
+        self.check_not_paused(PauseScope::Approve);
+        if token_ids.is_empty() {
+            panic!("NonFungibleToken: nothing to stake");
+        }
+
+        for token_id in &token_ids {
+            let token_owner = *self
+                .owner_by_id
+                .get(token_id)
+                .expect("NonFungibleToken: token does not exist");
+            self.check_owner(&token_owner);
+            self.check_not_pending(*token_id);
+            self.check_not_auctioned(*token_id);
+            self.check_not_nested(*token_id);
+            // the stake replaces the listing if any
+            self.listings.remove(token_id);
+        }
+        let stake = self.settle_points(&owner);
+        for token_id in &token_ids {
+            if !stake.token_ids.insert(*token_id) {
+                panic!("NonFungibleToken: token is staked");
+            }
+        }
+
+        NftEvent::Staked { owner, token_ids }
+    }
+    /// Stop staking `token_ids` of `msg::source()`, the accrued points stay claimable
+    fn unstake(&mut self, token_ids: Vec<TokenId>) -> NftEvent {
+        let owner = msg::source();
+        if token_ids.is_empty() {
+            panic!("NonFungibleToken: nothing to unstake");
+        }
+
+        let stake = self.settle_points(&owner);
+        for token_id in &token_ids {
+            if !stake.token_ids.remove(token_id) {
+                panic!("NonFungibleToken: token is not staked");
+            }
+        }
+        self.remove_empty_stake(&owner);
+
+        NftEvent::Unstaked { owner, token_ids }
+    }
+    /// Pay the accrued points of `owner` in the reward token,
+    /// the points are kept if the reward token program fails the transfer
+    async fn claim_rewards(&mut self, owner: ActorId) -> NftEvent {
+        let source = msg::source();
+        if source != owner && !self.is_delegate(&owner, &source, None) {
+            panic!("NonFungibleToken: access denied");
+        }
+        let reward_token = self
+            .config
+            .reward_token
+            .expect("NonFungibleToken: rewards are not paid out");
+        let stake = self.settle_points(&owner);
+        let amount = stake.points;
+        if amount == 0 {
+            panic!("NonFungibleToken: no points to claim");
+        }
+        stake.points = 0;
+
+        let program_id = exec::program_id();
+        let reply = msg::send_for_reply_as::<_, RewardTokenEvent>(
+            reward_token,
+            RewardTokenAction::Transfer {
+                from: program_id,
+                to: owner,
+                amount,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message")
+        .up_to(Some(SAFE_TRANSFER_TIMEOUT))
+        .expect("Invalid wait duration")
+        .await;
+
+        // only a reply confirming this very transfer pays the points
+        let paid = matches!(
+            reply,
+            Ok(RewardTokenEvent::Transfer { from, to, amount: transferred })
+                if from == program_id && to == owner && transferred == amount
+        );
+        if !paid {
+            // the stake may have changed meanwhile, so only the claimed points are returned
+            let stake = self.stakes.entry(owner).or_default();
+            stake.points = stake.points.saturating_add(amount);
+
+            return NftEvent::RewardsClaimRejected { owner, amount };
+        }
+        self.remove_empty_stake(&owner);
+
+        NftEvent::RewardsClaimed { owner, amount }
+    }
+    /// Accrue the points of `owner` up to now
+    fn settle_points(&mut self, owner: &ActorId) -> &mut Stake {
+        let index = self.staking_index();
+        let stake = self.stakes.entry(*owner).or_default();
+        let staked = stake.token_ids.len() as u128;
+        stake.points = stake
+            .points
+            .saturating_add(staked.saturating_mul(index - stake.index));
+        stake.index = index;
+
+        stake
+    }
+    fn remove_empty_stake(&mut self, owner: &ActorId) {
+        if self
+            .stakes
+            .get(owner)
+            .is_some_and(|stake| stake.token_ids.is_empty() && stake.points == 0)
+        {
+            self.stakes.remove(owner);
+        }
+    }
+    /// Get the points accrued per staked token, they don't grow while the program is frozen
+    fn staking_index(&self) -> u128 {
+        if self.frozen {
+            return self.staking_index;
+        }
+        let elapsed = current_timestamp().saturating_sub(self.staking_updated_at);
+
+        self.staking_index
+            .saturating_add(self.config.staking_rate.saturating_mul(u128::from(elapsed)))
+    }
+    /// Fix the points accrued per staked token up to now
+    fn update_staking_index(&mut self) {
+        self.staking_index = self.staking_index();
+        self.staking_updated_at = current_timestamp();
+    }
+    /// Get the staked tokens and the accrued points of `owner`
+    fn stake_info(&self, owner: &ActorId) -> StakeInfo {
+        let Some(stake) = self.stakes.get(owner) else {
+            return StakeInfo::default();
+        };
+        let mut token_ids: Vec<TokenId> = stake.token_ids.iter().cloned().collect();
+        token_ids.sort_unstable();
+        let staked = token_ids.len() as u128;
+
+        StakeInfo {
+            token_ids,
+            points: stake
+                .points
+                .saturating_add(staked.saturating_mul(self.staking_index() - stake.index)),
+        }
+    }
+    /// Get `ActorId` of the nft owner with `token_id`
+    fn owner(&self, token_id: TokenId) -> NftEvent {
+        let owner = self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+
+        NftEvent::Owner {
+            owner: *owner,
+            token_id,
+        }
+    }
+    /// Get confirmation about approval to address `to` and `token_id`
+    fn is_approved_to(&self, to: &ActorId, token_id: TokenId) -> NftEvent {
+        if !self.owner_by_id.contains_key(&token_id) {
+            panic!("Token does not exist")
+        }
+
+        NftEvent::CheckIfApproved {
+            to: *to,
+            token_id,
+            approved: self.active_approval(token_id) == Some(*to),
+        }
+    }
+    /// Get the account approved for `token_id`, ignoring an expired approval.
+    /// Read-only, so `CheckIfApproved` can't change the state of a frozen program
+    fn active_approval(&self, token_id: TokenId) -> Option<ActorId> {
+        self.token_approvals
+            .get(&token_id)
+            .filter(|approval| !is_expired(approval.expires_at))
+            .map(|approval| approval.account)
+    }
+
+    /// Checking the configuration with current contract data
+    fn check_config(&self) {
+        if self.config.supply_limit <= self.token_metadata_by_id.len() as u128 {
+            panic!(
+                "Mint impossible because max minting count {} limit exceeded",
+                self.config.supply_limit
+            );
+        }
+    }
+
+    /// Check for ZERO_ID address
+    fn check_zero_address(&self, account: &ActorId) {
+        if account == &ZERO_ID {
+            panic!("NonFungibleToken: zero address");
+        }
+    }
+
+    /// Checks that `msg::source()` is the owner of the token with indicated `token_id`
+    fn check_owner(&self, owner: &ActorId) {
+        if owner != &msg::source() {
+            panic!("NonFungibleToken: access denied");
+        }
+    }
+
+    /// Checks that `msg::source()` is the owner of the collection
+    fn check_collection_owner(&self) {
+        if self.owner != msg::source() {
+            panic!("NonFungibleToken: not authorized");
+        }
+    }
+
+    /// Checks that `msg::source()` is allowed to manage the token with indicated `token_id`
+    fn can_transfer(&mut self, token_id: TokenId, owner: &ActorId) {
+        if self.is_authorized(token_id, owner, &msg::source()) {
+            return;
+        }
+        self.check_owner(owner);
+    }
+    /// Checks that `account` is the owner, the approved account or an operator of `token_id`
+    fn is_authorized(&self, token_id: TokenId, owner: &ActorId, account: &ActorId) -> bool {
+        account == owner
+            || self.active_approval(token_id) == Some(*account)
+            || self.is_operator(owner, account)
+    }
+    /// Checks that `operator` is allowed to manage all tokens of `owner`
+    fn is_operator(&self, owner: &ActorId, operator: &ActorId) -> bool {
+        self.operator_approvals
+            .get(owner)
+            .is_some_and(|operators| operators.contains(operator))
+    }
+    /// Checks that `token_id` isn't bound to its owner
+    fn check_transferable(&self, token_id: TokenId) {
+        if self.is_soulbound(token_id) {
+            panic!("NonFungibleToken: token is soulbound");
+        }
+    }
+    fn is_soulbound(&self, token_id: TokenId) -> bool {
+        !self.config.transferable || self.soulbound.contains(&token_id)
+    }
+    /// Checks that `token_id` isn't locked
+    fn check_not_locked(&mut self, token_id: TokenId) {
+        if self.active_lock(token_id).is_some() {
+            panic!("NonFungibleToken: token is locked");
+        }
+    }
+    /// Get the lock of `token_id`, dropping it if it has expired
+    fn active_lock(&mut self, token_id: TokenId) -> Option<TokenLock> {
+        let lock = *self.token_locks.get(&token_id)?;
+        if is_expired(Some(lock.until)) {
+            self.token_locks.remove(&token_id);
+            return None;
+        }
+
+        Some(lock)
+    }
+    /// Checks that `token_id` of `owner` isn't staked
+    fn check_not_staked(&self, owner: &ActorId, token_id: TokenId) {
+        if self
+            .stakes
+            .get(owner)
+            .is_some_and(|stake| stake.token_ids.contains(&token_id))
+        {
+            panic!("NonFungibleToken: token is staked");
+        }
+    }
+    /// Checks that `token_id` isn't held by another token
+    fn check_not_nested(&self, token_id: TokenId) {
+        if self.token_parents.contains_key(&token_id) {
+            panic!("NonFungibleToken: token is nested");
+        }
+    }
+    /// Checks that `token_id` isn't held by an auction
+    fn check_not_auctioned(&self, token_id: TokenId) {
+        if self.auctions.contains_key(&token_id) {
+            panic!("NonFungibleToken: token is auctioned");
+        }
+    }
+    /// Checks that `token_id` isn't waiting for a `SafeTransfer` receiver
+    fn check_not_pending(&self, token_id: TokenId) {
+        if self.pending_transfers.contains(&token_id) {
+            panic!("NonFungibleToken: token transfer is pending");
+        }
+    }
+    /// Checks that the actions covered by `scope` are not paused
+    fn check_not_paused(&self, scope: PauseScope) {
+        let paused = match scope {
+            PauseScope::Mint => self.paused.mint,
+            PauseScope::Transfer => self.paused.transfer,
+            PauseScope::Approve => self.paused.approve,
+            PauseScope::Burn => self.paused.burn,
+            PauseScope::All => {
+                self.paused.mint || self.paused.transfer || self.paused.approve || self.paused.burn
+            }
+        };
+        if paused {
+            panic!("NonFungibleToken: {:?} is paused", scope);
+        }
+    }
+
+    /// Set collection's name
+    fn set_name(&mut self, name: &String) -> NftEvent {
+        self.check_collection_owner();
+        self.collection.name = name.to_string();
+
+        NftEvent::NameChanged {
+            name: name.to_string(),
+        }
+    }
+
+    /// Set collection's description
+    fn set_description(&mut self, description: &String) -> NftEvent {
+        self.check_collection_owner();
+        self.collection.description = description.to_string();
+
+        NftEvent::DescriptionChanged {
+            description: description.to_string(),
+        }
+    }
+
+    /// Set collection's symbol
+    fn set_symbol(&mut self, symbol: &String) -> NftEvent {
+        self.check_collection_owner();
+        self.collection.symbol = symbol.to_string();
+
+        NftEvent::SymbolChanged {
+            symbol: symbol.to_string(),
+        }
+    }
+
+    /// Set collection's base_uri
+    fn set_base_uri(&mut self, base_uri: &String) -> NftEvent {
+        self.check_collection_owner();
+        self.check_uri_template_not_frozen();
+        self.collection.base_uri = base_uri.to_string();
+
+        NftEvent::BaseUriChanged {
+            base_uri: base_uri.to_string(),
+        }
+    }
+
+    /// Set how token ids are written into the token URIs after `base_uri`
+    fn set_uri_template(&mut self, suffix: String, padding: u8) -> NftEvent {
+        self.check_collection_owner();
+        self.check_uri_template_not_frozen();
+        check_uri_padding(padding);
+        self.collection.uri_suffix = suffix.clone();
+        self.collection.uri_padding = padding;
+
+        NftEvent::UriTemplateChanged { suffix, padding }
+    }
+
+    /// Update the marketplace-facing fields of the collection
+    fn update_collection(&mut self, update: CollectionUpdate) -> NftEvent {
+        self.check_collection_owner();
+        let CollectionUpdate {
+            banner,
+            logo,
+            external_link,
+            social_links,
+            category,
+            contract_uri,
+        } = update;
+
+        let mut collection = self.collection.clone();
+        if let Some(banner) = banner {
+            collection.banner = banner;
+        }
+        if let Some(logo) = logo {
+            collection.logo = logo;
+        }
+        if let Some(external_link) = external_link {
+            collection.external_link = external_link;
+        }
+        if let Some(social_links) = social_links {
+            collection.social_links = social_links;
+        }
+        if let Some(category) = category {
+            collection.category = category;
+        }
+        if let Some(contract_uri) = contract_uri {
+            collection.contract_uri = contract_uri;
+        }
+        check_collection(&collection);
+        self.collection = collection.clone();
+
+        NftEvent::CollectionUpdated { collection }
+    }
+
+    /// Replace the metadata of `token_id`
+    fn set_token_metadata(&mut self, token_id: TokenId, metadata: TokenMetadata) -> NftEvent {
+        self.check_metadata_manager();
+        self.check_metadata_not_frozen(token_id);
+        let token_metadata = self
+            .token_metadata_by_id
+            .get_mut(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+        // the reference is read from the token URI, an empty one keeps the current URI
+        if !metadata.reference.is_empty() {
+            self.token_uris.insert(token_id, metadata.reference.clone());
+        }
+        *token_metadata = metadata;
+
+        NftEvent::MetadataUpdated { token_id }
+    }
+
+    /// Set the name of `token_id` on behalf of its owner
+    fn set_display_name(&mut self, token_id: TokenId, name: String) -> NftEvent {
+        if !self.config.owner_metadata_updates {
+            panic!("NonFungibleToken: owner metadata updates are disabled");
+        }
+        let owner = *self
+            .owner_by_id
+            .get(&token_id)
+            .expect("NonFungibleToken: token does not exist");
+        let source = msg::source();
+        if source != owner && !self.is_delegate(&owner, &source, Some(token_id)) {
+            panic!("NonFungibleToken: access denied");
+        }
+        if name.is_empty() || name.len() > DISPLAY_NAME_MAX_LEN {
+            panic!(
+                "NonFungibleToken: display name must have 1 to {} bytes",
+                DISPLAY_NAME_MAX_LEN
+            );
+        }
+        self.check_metadata_not_frozen(token_id);
+        self.token_metadata_by_id
+            .entry(token_id)
+            .and_modify(|metadata| metadata.name = name);
+
+        NftEvent::MetadataUpdated { token_id }
+    }
+
+    /// Notify indexers that the metadata of the tokens in the range has changed
+    fn refresh_metadata(&mut self, from_token_id: TokenId, to_token_id: TokenId) -> NftEvent {
+        self.check_metadata_manager();
+        if from_token_id > to_token_id {
+            panic!("NonFungibleToken: empty token range");
+        }
+
+        NftEvent::BatchMetadataUpdated {
+            from_token_id,
+            to_token_id,
+        }
+    }
+
+    /// Set the account allowed to update token metadata, `ZERO_ID` removes it
+    fn set_metadata_manager(&mut self, manager: &ActorId) -> NftEvent {
+        self.check_collection_owner();
+        self.config.metadata_manager = *manager;
+
+        NftEvent::MetadataManagerChanged { manager: *manager }
+    }
+
+    /// Allow or forbid token owners to set the display name of their tokens
+    fn set_owner_metadata_updates(&mut self, enabled: bool) -> NftEvent {
+        self.check_collection_owner();
+        self.config.owner_metadata_updates = enabled;
+
+        NftEvent::OwnerMetadataUpdatesChanged { enabled }
+    }
+
+    /// Replace the attributes of every token in the batch
+    fn set_attributes(&mut self, attributes: Vec<(TokenId, Vec<Attribute>)>) -> NftEvent {
+        self.check_metadata_manager();
+        if attributes.is_empty() {
+            panic!("NonFungibleToken: nothing to set");
+        }
+        if attributes.len() > ATTRIBUTES_BATCH_LIMIT {
+            panic!(
+                "NonFungibleToken: batch limit {} exceeded",
+                ATTRIBUTES_BATCH_LIMIT
+            );
+        }
+
+        let mut token_ids = Vec::with_capacity(attributes.len());
+        for (token_id, token_attributes) in attributes {
+            if !self.owner_by_id.contains_key(&token_id) {
+                panic!("NonFungibleToken: token {} does not exist", token_id);
+            }
+            self.check_metadata_not_frozen(token_id);
+            check_attributes(&token_attributes);
+            if token_attributes.is_empty() {
+                self.token_attributes.remove(&token_id);
+            } else {
+                self.token_attributes.insert(token_id, token_attributes);
+            }
+            token_ids.push(token_id);
+        }
+
+        NftEvent::AttributesSet { token_ids }
+    }
+
+    /// Override the URI of `token_id`, `None` falls back to the collection template
+    fn set_token_uri(&mut self, token_id: TokenId, uri: Option<String>) -> NftEvent {
+        self.check_metadata_manager();
+        if !self.owner_by_id.contains_key(&token_id) {
+            panic!("NonFungibleToken: token does not exist");
+        }
+        self.check_metadata_not_frozen(token_id);
+        match &uri {
+            Some(uri) => {
+                if uri.is_empty() {
+                    panic!("NonFungibleToken: empty token URI");
+                }
+                self.token_uris.insert(token_id, uri.clone());
+            }
+            None => {
+                self.token_uris.remove(&token_id);
+            }
+        }
+
+        NftEvent::TokenUriChanged { token_id, uri }
+    }
+
+    /// Permanently freeze the metadata and URI of `token_id`
+    fn freeze_metadata(&mut self, token_id: TokenId) -> NftEvent {
+        self.check_metadata_manager();
+        self.check_metadata_not_frozen(token_id);
+        let uri = self
+            .token_uri(token_id)
+            .expect("NonFungibleToken: token does not exist");
+        self.frozen_metadata.insert(token_id);
+
+        NftEvent::MetadataFrozen { token_id, uri }
+    }
+
+    /// Permanently freeze the metadata of all tokens and the collection URI template
+    fn freeze_all_metadata(&mut self) -> NftEvent {
+        self.check_metadata_manager();
+        if self.metadata_frozen {
+            panic!("NonFungibleToken: metadata is already frozen");
+        }
+        self.metadata_frozen = true;
+        self.frozen_metadata.clear();
+
+        NftEvent::AllMetadataFrozen
+    }
+
+    /// Check whether the metadata of `token_id` is frozen
+    fn is_metadata_frozen(&self, token_id: TokenId) -> bool {
+        self.metadata_frozen || self.frozen_metadata.contains(&token_id)
+    }
+
+    fn check_metadata_not_frozen(&self, token_id: TokenId) {
+        if self.is_metadata_frozen(token_id) {
+            panic!("NonFungibleToken: metadata of token {} is frozen", token_id);
+        }
+    }
+
+    /// Check that changing the URI template doesn't change the URI of a frozen token
+    fn check_uri_template_not_frozen(&self) {
+        if self.metadata_frozen {
+            panic!("NonFungibleToken: metadata is frozen");
+        }
+        if let Some(token_id) = self
+            .frozen_metadata
+            .iter()
+            .find(|token_id| !self.token_uris.contains_key(*token_id))
+        {
+            panic!("NonFungibleToken: metadata of token {} is frozen", token_id);
+        }
+    }
+
+    /// Count the tokens with each value of the `key` attribute
+    fn trait_counts(&self, key: &str) -> Vec<(AttributeValue, u128)> {
+        let mut counts: Vec<(AttributeValue, u128)> = Vec::new();
+        for attribute in self.token_attributes.values().flatten() {
+            if attribute.key != key {
+                continue;
+            }
+            match counts
+                .iter_mut()
+                .find(|(value, _)| *value == attribute.value)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((attribute.value.clone(), 1)),
+            }
+        }
+        counts.sort_unstable();
+
+        counts
+    }
+
+    /// Checks that `msg::source()` is the collection owner or the metadata manager
+    fn check_metadata_manager(&self) {
+        let source = msg::source();
+        if source != self.owner && source != self.config.metadata_manager {
+            panic!("NonFungibleToken: not authorized");
+        }
+    }
+
+    // Set withdraw_address.
+    fn set_withdraw_wallet(&mut self, withdraw_wallet: &ActorId) -> NftEvent {
+        self.check_collection_owner();
+        self.config.withdraw_wallet = *withdraw_wallet;
+
+        NftEvent::WithdrawWalletChanged {
+            withdraw_wallet: *withdraw_wallet,
+        }
+    }
+
+    /// Set supply limit.
+    fn set_supply_limit(&mut self, supply_limit: u128) -> NftEvent {
+        self.check_collection_owner();
+        self.config.supply_limit = supply_limit;
+
+        NftEvent::SupplyLimitChanged { supply_limit }
+    }
+
+    /// Set mint limit.
+    fn set_mint_limit(&mut self, mint_limit: u128) -> NftEvent {
+        self.check_collection_owner();
+        self.config.mint_limit = mint_limit;
+
+        NftEvent::MintLimitChanged { mint_limit }
+    }
+
+    /// Set mint price.
+    fn set_mint_price(&mut self, mint_price: u128) -> NftEvent {
+        self.check_collection_owner();
+        self.config.mint_price = mint_price;
+
+        NftEvent::MintPriceChanged { mint_price }
+    }
+
+    // Set sale time.
+    fn set_sale_time(&mut self, sale_time: u64) -> NftEvent {
+        self.check_collection_owner();
+        self.config.sale_time = sale_time;
+
+        NftEvent::SaleTimeChanged {
+            sale_time,
+            sale_active: sale_time <= exec::block_timestamp() / 1000,
+        }
+    }
+
+    fn sale_active(&self) -> bool {
+        let current_ts = exec::block_timestamp() / 1000;
+
+        self.config.sale_time <= current_ts
+    }
+
+    fn toggle_sale_active(&mut self) -> NftEvent {
+        self.check_collection_owner();
+
+        let sale_active_status = self.sale_active();
+        if sale_active_status {
+            self.set_sale_time(SALE_TIME_MAX);
+        } else {
+            self.set_sale_time(0);
+        }
+
+        NftEvent::SaleActiveChanged {
+            sale_active: !sale_active_status,
+        }
+    }
+
+    /// Set the royalty applied to tokens without their own royalty
+    fn set_default_royalty(&mut self, receiver: &ActorId, bps: u16) -> NftEvent {
+        self.check_collection_owner();
+        check_royalty(receiver, bps);
+        self.config.royalty_receiver = *receiver;
+        self.config.royalty_bps = bps;
+
+        NftEvent::DefaultRoyaltyChanged {
+            receiver: *receiver,
+            bps,
+        }
+    }
+
+    /// Override the royalty of `token_id`, `None` falls back to the default one
+    fn set_token_royalty(&mut self, token_id: TokenId, royalty: Option<Royalty>) -> NftEvent {
+        self.check_collection_owner();
+        if !self.owner_by_id.contains_key(&token_id) {
+            panic!("NonFungibleToken: token does not exist");
+        }
+        match royalty {
+            Some(royalty) => {
+                check_royalty(&royalty.receiver, royalty.bps);
+                self.token_royalties.insert(token_id, royalty);
+            }
+            None => {
+                self.token_royalties.remove(&token_id);
+            }
+        }
+
+        NftEvent::TokenRoyaltyChanged { token_id, royalty }
+    }
+
+    /// Get the royalty receiver and amount owed for selling `token_id` at `sale_price`
+    fn royalty_info(&self, token_id: TokenId, sale_price: u128) -> Option<RoyaltyInfo> {
+        if !self.owner_by_id.contains_key(&token_id) {
+            return None;
+        }
+        let royalty = self
+            .token_royalties
+            .get(&token_id)
+            .cloned()
+            .unwrap_or(Royalty {
+                receiver: self.config.royalty_receiver,
+                bps: self.config.royalty_bps,
+            });
+
+        Some(RoyaltyInfo {
+            receiver: royalty.receiver,
+            amount: bps_of(sale_price, royalty.bps),
+        })
+    }
+
+    /// Pay the royalty of `token_id` and the platform `fee` out of `price`
+    /// and the rest to `seller`, returns the paid royalty
+    fn pay_sale(&self, token_id: TokenId, seller: &ActorId, price: u128, fee: u128) -> u128 {
+        let royalty = self
+            .royalty_info(token_id, price)
+            .expect("NonFungibleToken: token does not exist");
+
+        send_value(&royalty.receiver, royalty.amount);
+        send_value(&self.config.dev_wallet, fee);
+        send_value(seller, price - royalty.amount - fee);
+
+        royalty.amount
+    }
+
+    /// Require approved accounts and operators to use `SaleTransfer`
+    fn set_royalty_enforced(&mut self, enforced: bool) -> NftEvent {
+        self.check_collection_owner();
+        self.config.royalty_enforced = enforced;
+
+        NftEvent::RoyaltyEnforcedChanged { enforced }
+    }
+
+    /// Set the fee charged on listing sales
+    fn set_platform_fee(&mut self, bps: u16) -> NftEvent {
+        self.check_collection_owner();
+        check_platform_fee(bps);
+        self.config.platform_fee_bps = bps;
+
+        NftEvent::PlatformFeeChanged { bps }
+    }
+
+    /// Allow or forbid tokens of `collection` to hold tokens of this collection
+    fn set_nesting_collection(&mut self, collection: &ActorId, allowed: bool) -> NftEvent {
+        self.check_collection_owner();
+        self.check_zero_address(collection);
+        self.config
+            .nesting_collections
+            .retain(|nesting_collection| nesting_collection != collection);
+        if allowed {
+            self.config.nesting_collections.push(*collection);
+        }
+
+        NftEvent::NestingCollectionChanged {
+            collection: *collection,
+            allowed,
+        }
+    }
+
+    /// Set the reward points accrued per staked token per second
+    fn set_staking_rate(&mut self, rate: u128) -> NftEvent {
+        self.check_collection_owner();
+        // points accrued so far keep the previous rate
+        self.update_staking_index();
+        self.config.staking_rate = rate;
+
+        NftEvent::StakingRateChanged { rate }
     }
 
-    /// Set mint limit.
-    fn set_mint_limit(&mut self, mint_limit: u128) -> NftEvent {
+    /// Set the fungible token program paying out reward points, `None` stops payouts
+    fn set_reward_token(&mut self, reward_token: Option<ActorId>) -> NftEvent {
         self.check_collection_owner();
-        self.config.mint_limit = mint_limit;
+        self.config.reward_token = reward_token;
 
-        NftEvent::MintLimitChanged { mint_limit }
+        NftEvent::RewardTokenChanged { reward_token }
     }
 
-    /// Set mint price.
-    fn set_mint_price(&mut self, mint_price: u128) -> NftEvent {
+    /// Checks that only the owner moves the token without a sale when royalties are enforced
+    fn check_royalty_free_transfer(&self, owner: &ActorId) {
+        if self.config.royalty_enforced && *owner != msg::source() {
+            panic!("NonFungibleToken: royalties are enforced, use SaleTransfer");
+        }
+    }
+
+    /// Halt the actions covered by `scope`
+    fn pause(&mut self, scope: PauseScope) -> NftEvent {
         self.check_collection_owner();
-        self.config.mint_price = mint_price;
+        self.set_paused(scope, true);
 
-        NftEvent::MintPriceChanged { mint_price }
+        NftEvent::Paused { scope }
     }
 
-    // Set sale time.
-    fn set_sale_time(&mut self, sale_time: u64) -> NftEvent {
+    /// Resume the actions covered by `scope`
+    fn unpause(&mut self, scope: PauseScope) -> NftEvent {
         self.check_collection_owner();
-        self.config.sale_time = sale_time;
+        self.set_paused(scope, false);
 
-        NftEvent::SaleTimeChanged {
-            sale_time,
-            sale_active: sale_time <= exec::block_timestamp() / 1000,
+        NftEvent::Unpaused { scope }
+    }
+
+    fn set_paused(&mut self, scope: PauseScope, paused: bool) {
+        match scope {
+            PauseScope::Mint => self.paused.mint = paused,
+            PauseScope::Transfer => self.paused.transfer = paused,
+            PauseScope::Approve => self.paused.approve = paused,
+            PauseScope::Burn => self.paused.burn = paused,
+            PauseScope::All => {
+                self.paused = PauseStatus {
+                    mint: paused,
+                    transfer: paused,
+                    approve: paused,
+                    burn: paused,
+                }
+            }
         }
     }
 
-    fn sale_active(&self) -> bool {
-        let current_ts = exec::block_timestamp() / 1000;
+    /// Freeze the program before exporting its state, cancelling every auction
+    /// and refunding every open offer and highest bid
+    fn freeze(&mut self) -> NftEvent {
+        self.check_collection_owner();
+        if !self.pending_transfers.is_empty() {
+            panic!("NonFungibleToken: token transfers are pending");
+        }
+        // escrowed value stays in this program, so offers and auctions aren't migrated
+        for (_, offer) in self.offers.drain() {
+            send_value(&offer.buyer, offer.amount);
+        }
+        for (_, auction) in self.auctions.drain() {
+            if let Some(bid) = auction.highest_bid {
+                send_value(&bid.bidder, bid.amount);
+            }
+        }
+        // exported points don't depend on the time of the export
+        self.update_staking_index();
+        self.frozen = true;
 
-        self.config.sale_time <= current_ts
+        NftEvent::Frozen
     }
 
-    fn toggle_sale_active(&mut self) -> NftEvent {
+    /// Unfreeze the program, e.g. when a migration is aborted
+    fn unfreeze(&mut self) -> NftEvent {
+        self.check_collection_owner();
+        if !self.frozen {
+            panic!("NonFungibleToken: not frozen");
+        }
+        if self.import.is_some() {
+            panic!("NonFungibleToken: import in progress");
+        }
+        self.frozen = false;
+        // `freeze` updated the index, no points accrue while frozen
+        self.staking_updated_at = current_timestamp();
+
+        NftEvent::Unfrozen
+    }
+
+    /// Start loading a state exported from another program.
+    /// Calling it again during an import discards the records loaded so far.
+    fn begin_import(&mut self, header: MigrationHeader) -> NftEvent {
         self.check_collection_owner();
+        if self.import.is_none() && self.token_id != 0 {
+            panic!("NonFungibleToken: import requires an empty collection");
+        }
 
-        let sale_active_status = self.sale_active();
-        if sale_active_status {
-            self.set_sale_time(SALE_TIME_MAX);
-        } else {
-            self.set_sale_time(0);
+        self.owner_by_id.clear();
+        self.token_approvals.clear();
+        self.token_metadata_by_id.clear();
+        self.tokens_for_owner.clear();
+        self.balances.clear();
+        self.soulbound.clear();
+        self.token_locks.clear();
+        self.token_royalties.clear();
+        self.listings.clear();
+        self.token_users.clear();
+        self.rental_terms.clear();
+        self.token_parents.clear();
+        self.token_children.clear();
+        self.stakes.clear();
+        self.staking_index = 0;
+        self.token_attributes.clear();
+        self.token_uris.clear();
+        self.frozen_metadata.clear();
+        self.operator_approvals.clear();
+        self.delegations.clear();
+        self.frozen = true;
+
+        let event = NftEvent::ImportStarted {
+            records: header.records,
+            checksum: header.checksum,
+        };
+        self.import = Some(ImportProgress {
+            header,
+            records: 0,
+            checksum: 0,
+        });
+
+        event
+    }
+
+    /// Load a chunk of records returned by `StateQuery::ExportRecords`
+    fn import_records(&mut self, records: Vec<MigrationRecord>) -> NftEvent {
+        self.check_collection_owner();
+        if self.import.is_none() {
+            panic!("NonFungibleToken: no import in progress");
+        }
+        if records.len() as u64 > MIGRATION_CHUNK_LIMIT {
+            panic!(
+                "NonFungibleToken: chunk limit {} exceeded",
+                MIGRATION_CHUNK_LIMIT
+            );
         }
 
-        NftEvent::SaleActiveChanged {
-            sale_active: !sale_active_status,
+        let imported = records.len() as u64;
+        let checksum = migration_checksum(&records);
+        for record in records {
+            match record {
+                MigrationRecord::Token(record) => self.import_token(record),
+                MigrationRecord::Operators { owner, operators } => {
+                    if self.operator_approvals.contains_key(&owner) {
+                        panic!("NonFungibleToken: operators already imported");
+                    }
+                    self.operator_approvals
+                        .insert(owner, operators.into_iter().collect());
+                }
+                MigrationRecord::Delegations { owner, delegations } => {
+                    if self.delegations.contains_key(&owner) {
+                        panic!("NonFungibleToken: delegations already imported");
+                    }
+                    self.delegations.insert(owner, delegations);
+                }
+                MigrationRecord::Stake {
+                    owner,
+                    token_ids,
+                    points,
+                } => {
+                    if self.stakes.contains_key(&owner) {
+                        panic!("NonFungibleToken: stake already imported");
+                    }
+                    self.stakes.insert(
+                        owner,
+                        Stake {
+                            token_ids: token_ids.into_iter().collect(),
+                            points,
+                            index: 0,
+                        },
+                    );
+                }
+            }
+        }
+
+        let progress = self.import.as_mut().expect("Checked above");
+        progress.records += imported;
+        progress.checksum = progress.checksum.wrapping_add(checksum);
+
+        NftEvent::RecordsImported { imported }
+    }
+
+    fn import_token(&mut self, record: TokenRecord) {
+        let TokenRecord {
+            token_id,
+            owner,
+            approval,
+            metadata,
+            soulbound,
+            lock,
+            royalty,
+            listing,
+            user,
+            rental_terms,
+            parent,
+            attributes,
+            uri,
+            metadata_frozen,
+        } = record;
+
+        if self.owner_by_id.contains_key(&token_id) {
+            panic!("NonFungibleToken: token {} already imported", token_id);
+        }
+        self.owner_by_id.insert(token_id, owner);
+        self.add_token_to_owner(&owner, token_id);
+        if let Some(approval) = approval {
+            self.token_approvals.insert(token_id, approval);
+        }
+        self.token_metadata_by_id.insert(token_id, metadata);
+        if soulbound {
+            self.soulbound.insert(token_id);
+        }
+        if let Some(lock) = lock {
+            self.token_locks.insert(token_id, lock);
+        }
+        if let Some(royalty) = royalty {
+            self.token_royalties.insert(token_id, royalty);
+        }
+        if let Some(listing) = listing {
+            self.listings.insert(token_id, listing);
+        }
+        if let Some(user) = user {
+            self.token_users.insert(token_id, user);
+        }
+        if let Some(rental_terms) = rental_terms {
+            self.rental_terms.insert(token_id, rental_terms);
+        }
+        if let Some(parent) = parent {
+            self.token_parents.insert(token_id, parent);
+            if parent.collection.is_none() {
+                self.token_children
+                    .entry(parent.token_id)
+                    .or_default()
+                    .insert(token_id);
+            }
+        }
+        if !attributes.is_empty() {
+            self.token_attributes.insert(token_id, attributes);
+        }
+        if let Some(uri) = uri {
+            self.token_uris.insert(token_id, uri);
+        }
+        if metadata_frozen {
+            self.frozen_metadata.insert(token_id);
+        }
+    }
+
+    /// Verify the loaded records against the header and unfreeze the program
+    fn finish_import(&mut self) -> NftEvent {
+        self.check_collection_owner();
+        let progress = self
+            .import
+            .as_ref()
+            .expect("NonFungibleToken: no import in progress");
+
+        if progress.records != progress.header.records {
+            panic!(
+                "NonFungibleToken: imported {} records, expected {}",
+                progress.records, progress.header.records
+            );
+        }
+        if progress.checksum != progress.header.checksum {
+            panic!("NonFungibleToken: checksum mismatch");
+        }
+
+        let ImportProgress { header, .. } = self.import.take().expect("Checked above");
+        self.owner = header.owner;
+        self.collection = header.collection;
+        self.config = header.config;
+        self.token_id = header.token_id;
+        self.paused = header.paused;
+        self.metadata_frozen = header.metadata_frozen;
+        self.frozen = false;
+        self.staking_updated_at = current_timestamp();
+
+        NftEvent::ImportFinished {
+            records: header.records,
+            checksum: header.checksum,
+        }
+    }
+
+    fn token_record(&self, token_id: TokenId) -> MigrationRecord {
+        MigrationRecord::Token(TokenRecord {
+            token_id,
+            owner: self.owner_by_id[&token_id],
+            approval: self.token_approvals.get(&token_id).cloned(),
+            metadata: self.token_metadata_by_id[&token_id].clone(),
+            soulbound: self.soulbound.contains(&token_id),
+            lock: self.token_locks.get(&token_id).copied(),
+            royalty: self.token_royalties.get(&token_id).cloned(),
+            listing: self.listings.get(&token_id).cloned(),
+            user: self.token_users.get(&token_id).cloned(),
+            rental_terms: self.rental_terms.get(&token_id).cloned(),
+            parent: self.token_parents.get(&token_id).cloned(),
+            attributes: self
+                .token_attributes
+                .get(&token_id)
+                .cloned()
+                .unwrap_or_default(),
+            uri: self.token_uris.get(&token_id).cloned(),
+            metadata_frozen: self.frozen_metadata.contains(&token_id),
+        })
+    }
+
+    fn operators_record(&self, owner: ActorId) -> MigrationRecord {
+        MigrationRecord::Operators {
+            owner,
+            operators: self.operator_approvals[&owner].iter().cloned().collect(),
+        }
+    }
+
+    fn delegations_record(&self, owner: ActorId) -> MigrationRecord {
+        MigrationRecord::Delegations {
+            owner,
+            delegations: self.delegations[&owner].clone(),
+        }
+    }
+
+    fn stake_record(&self, owner: ActorId) -> MigrationRecord {
+        let StakeInfo { token_ids, points } = self.stake_info(&owner);
+
+        MigrationRecord::Stake {
+            owner,
+            token_ids,
+            points,
+        }
+    }
+
+    /// Records of the exported state: tokens ordered by id,
+    /// then operator approvals, delegations and stakes ordered by owner
+    fn export_records(&self, offset: u64, limit: u64) -> Vec<MigrationRecord> {
+        let offset = offset as usize;
+        let limit = limit.min(MIGRATION_CHUNK_LIMIT) as usize;
+
+        let mut token_ids: Vec<TokenId> = self.owner_by_id.keys().cloned().collect();
+        token_ids.sort_unstable();
+        let mut owners: Vec<ActorId> = self.operator_approvals.keys().cloned().collect();
+        owners.sort_unstable();
+        let mut delegators: Vec<ActorId> = self.delegations.keys().cloned().collect();
+        delegators.sort_unstable();
+        let mut stakers: Vec<ActorId> = self.stakes.keys().cloned().collect();
+        stakers.sort_unstable();
+
+        let mut records: Vec<MigrationRecord> = token_ids
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|token_id| self.token_record(*token_id))
+            .collect();
+        let remaining = limit - records.len();
+        records.extend(
+            owners
+                .iter()
+                .skip(offset.saturating_sub(token_ids.len()))
+                .take(remaining)
+                .map(|owner| self.operators_record(*owner)),
+        );
+        let remaining = limit - records.len();
+        records.extend(
+            delegators
+                .iter()
+                .skip(offset.saturating_sub(token_ids.len() + owners.len()))
+                .take(remaining)
+                .map(|owner| self.delegations_record(*owner)),
+        );
+        let remaining = limit - records.len();
+        records.extend(
+            stakers
+                .into_iter()
+                .skip(offset.saturating_sub(token_ids.len() + owners.len() + delegators.len()))
+                .take(remaining)
+                .map(|owner| self.stake_record(owner)),
+        );
+
+        records
+    }
+
+    fn export_header(self) -> MigrationHeader {
+        let records: Vec<MigrationRecord> = self
+            .owner_by_id
+            .keys()
+            .map(|token_id| self.token_record(*token_id))
+            .chain(
+                self.operator_approvals
+                    .keys()
+                    .map(|owner| self.operators_record(*owner)),
+            )
+            .chain(
+                self.delegations
+                    .keys()
+                    .map(|owner| self.delegations_record(*owner)),
+            )
+            .chain(self.stakes.keys().map(|owner| self.stake_record(*owner)))
+            .collect();
+
+        MigrationHeader {
+            records: records.len() as u64,
+            checksum: migration_checksum(&records),
+            owner: self.owner,
+            collection: self.collection,
+            config: self.config,
+            token_id: self.token_id,
+            paused: self.paused,
+            metadata_frozen: self.metadata_frozen,
         }
     }
 }
 
-#[no_mangle]
-extern "C" fn handle() {
+#[gstd::async_main]
+async fn main() {
     let action: NftAction = msg::load().expect("Could not load NftAction");
     let nft = unsafe { NFT.as_mut().expect("`NFT` is not initialized.") };
+    if nft.frozen
+        && !matches!(
+            action,
+            NftAction::GetOwner { .. }
+                | NftAction::CheckIfApproved { .. }
+                | NftAction::Unfreeze {}
+                | NftAction::BeginImport { .. }
+                | NftAction::ImportRecords { .. }
+                | NftAction::FinishImport {}
+        )
+    {
+        panic!("NonFungibleToken: program is frozen");
+    }
     let result = match action {
         // NftAction::Mint { to, token_metadata } => nft.mint(&to, token_metadata),
         NftAction::Burn { token_id } => nft.burn(token_id),
         NftAction::Transfer { to, token_id } => nft.transfer(&to, token_id),
-        NftAction::Approve { to, token_id } => nft.approve(&to, token_id),
+        NftAction::BatchTransfer { transfers } => nft.batch_transfer(transfers),
+        NftAction::SafeTransfer {
+            to,
+            token_id,
+            data,
+            to_program,
+        } => nft.safe_transfer(to, token_id, data, to_program).await,
+        NftAction::SaleTransfer {
+            to,
+            token_id,
+            price,
+        } => nft.sale_transfer(&to, token_id, price),
+        NftAction::Approve {
+            to,
+            token_id,
+            expires_at,
+        } => nft.approve(&to, token_id, expires_at),
+        NftAction::RevokeApproval { token_id } => nft.revoke_approval(token_id),
         NftAction::GetOwner { token_id } => nft.owner(token_id),
         NftAction::CheckIfApproved { to, token_id } => nft.is_approved_to(&to, token_id),
+        NftAction::SetApprovalForAll { operator, approved } => {
+            nft.set_approval_for_all(&operator, approved)
+        }
+        NftAction::Lock {
+            token_id,
+            until,
+            locker,
+        } => nft.lock(token_id, until, &locker),
+        NftAction::Unlock { token_id } => nft.unlock(token_id),
+        NftAction::Delegate {
+            delegate,
+            token_ids,
+            expires_at,
+        } => nft.delegate(&delegate, token_ids, expires_at),
+        NftAction::RevokeDelegate { delegate } => nft.revoke_delegate(&delegate),
+        NftAction::Nest { token_id, parent } => nft.nest(token_id, parent).await,
+        NftAction::Unnest { token_id } => nft.unnest(token_id).await,
+        // staking
+        NftAction::Stake { token_ids } => nft.stake(token_ids),
+        NftAction::Unstake { token_ids } => nft.unstake(token_ids),
+        NftAction::ClaimRewards { owner } => nft.claim_rewards(owner).await,
+        // marketplace
+        NftAction::List {
+            token_id,
+            price,
+            expires_at,
+        } => nft.list(token_id, price, expires_at),
+        NftAction::Delist { token_id } => nft.delist(token_id),
+        NftAction::BuyListed { token_id } => nft.buy_listed(token_id),
+        NftAction::MakeOffer {
+            token_id,
+            expires_at,
+        } => nft.make_offer(token_id, expires_at),
+        NftAction::CancelOffer { token_id } => nft.cancel_offer(token_id),
+        NftAction::AcceptOffer {
+            token_id,
+            buyer,
+            collection_offer,
+        } => nft.accept_offer(token_id, &buyer, collection_offer),
+        NftAction::StartAuction {
+            token_id,
+            reserve_price,
+            min_increment,
+            ends_at,
+            extension,
+        } => nft.start_auction(token_id, reserve_price, min_increment, ends_at, extension),
+        NftAction::Bid { token_id } => nft.bid(token_id),
+        NftAction::SettleAuction { token_id } => nft.settle_auction(token_id),
+        NftAction::CancelAuction { token_id } => nft.cancel_auction(token_id),
+        // rentals
+        NftAction::SetUser {
+            token_id,
+            user,
+            expires,
+        } => nft.set_user(token_id, &user, expires),
+        NftAction::SetRentalTerms { token_id, terms } => nft.set_rental_terms(token_id, terms),
+        NftAction::Rent { token_id, duration } => nft.rent(token_id, duration),
         // change collection info
         NftAction::SetName { name } => nft.set_name(&name),
         NftAction::SetDescription { description } => nft.set_description(&description),
         NftAction::SetSymbol { symbol } => nft.set_symbol(&symbol),
         NftAction::SetBaseUri { base_uri } => nft.set_base_uri(&base_uri),
+        NftAction::SetUriTemplate { suffix, padding } => nft.set_uri_template(suffix, padding),
+        NftAction::UpdateCollection { update } => nft.update_collection(update),
+        // change token metadata
+        NftAction::SetTokenMetadata { token_id, metadata } => {
+            nft.set_token_metadata(token_id, metadata)
+        }
+        NftAction::SetDisplayName { token_id, name } => nft.set_display_name(token_id, name),
+        NftAction::RefreshMetadata {
+            from_token_id,
+            to_token_id,
+        } => nft.refresh_metadata(from_token_id, to_token_id),
+        NftAction::SetMetadataManager { manager } => nft.set_metadata_manager(&manager),
+        NftAction::SetOwnerMetadataUpdates { enabled } => nft.set_owner_metadata_updates(enabled),
+        NftAction::SetAttributes { attributes } => nft.set_attributes(attributes),
+        NftAction::SetTokenUri { token_id, uri } => nft.set_token_uri(token_id, uri),
+        NftAction::FreezeMetadata { token_id } => nft.freeze_metadata(token_id),
+        NftAction::FreezeAllMetadata {} => nft.freeze_all_metadata(),
         // change collection config
         NftAction::SetWithdrawWallet { withdraw_wallet } => {
             nft.set_withdraw_wallet(&withdraw_wallet)
@@ -498,8 +2779,34 @@ extern "C" fn handle() {
         NftAction::SetMintPrice { mint_price } => nft.set_mint_price(mint_price),
         NftAction::SetSaleTime { sale_time } => nft.set_sale_time(sale_time),
         NftAction::ToggleSaleActive {} => nft.toggle_sale_active(),
+        NftAction::SetDefaultRoyalty { receiver, bps } => nft.set_default_royalty(&receiver, bps),
+        NftAction::SetTokenRoyalty { token_id, royalty } => {
+            nft.set_token_royalty(token_id, royalty)
+        }
+        NftAction::SetRoyaltyEnforced { enforced } => nft.set_royalty_enforced(enforced),
+        NftAction::SetPlatformFee { bps } => nft.set_platform_fee(bps),
+        NftAction::SetNestingCollection {
+            collection,
+            allowed,
+        } => nft.set_nesting_collection(&collection, allowed),
+        NftAction::SetStakingRate { rate } => nft.set_staking_rate(rate),
+        NftAction::SetRewardToken { reward_token } => nft.set_reward_token(reward_token),
         NftAction::Buy { amount } => nft.buy(amount),
         NftAction::Reserve { amount } => nft.reserve(amount),
+        NftAction::Airdrop {
+            to,
+            amount,
+            soulbound,
+        } => nft.airdrop(&to, amount, soulbound),
+        // circuit breaker
+        NftAction::Pause { scope } => nft.pause(scope),
+        NftAction::Unpause { scope } => nft.unpause(scope),
+        // migration
+        NftAction::Freeze {} => nft.freeze(),
+        NftAction::Unfreeze {} => nft.unfreeze(),
+        NftAction::BeginImport { header } => nft.begin_import(header),
+        NftAction::ImportRecords { records } => nft.import_records(records),
+        NftAction::FinishImport {} => nft.finish_import(),
     };
     msg::reply(result, 0).expect("Failed to encode or reply with `NftEvent`.");
 }
@@ -534,22 +2841,25 @@ extern "C" fn state() {
             .expect("Unable to share the state");
         }
         StateQuery::TokenApprovals { token_id } => {
-            let approval = nft.token_approvals.get(&token_id).cloned();
+            let approval = nft
+                .token_approvals
+                .get(&token_id)
+                .filter(|approval| !is_expired(approval.expires_at))
+                .cloned();
             msg::reply(StateReply::TokenApprovals(approval), 0).expect("Unable to share the state");
         }
         StateQuery::TokenMetadata { token_id } => {
-            let token_metadata = nft.token_metadata_by_id.get(&token_id).cloned().unwrap();
-            msg::reply(
-                // StateReply::TokenMetadata(nft.token_metadata_by_id.get(&token_id).cloned()),
-                StateReply::TokenMetadata(Some(TokenMetadata {
-                    name: token_metadata.name,
-                    description: "".to_string(),
-                    media: "".to_string(),
-                    reference: nft.collection.base_uri.to_string() + &token_id.to_string(),
-                })),
-                0,
-            )
-            .expect("Unable to share the state");
+            msg::reply(StateReply::TokenMetadata(nft.token_metadata(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::TokenInfo { token_id } => {
+            let token_info = nft.token_metadata(token_id).map(|metadata| TokenInfo {
+                owner: nft.owner_by_id[&token_id],
+                metadata,
+                soulbound: nft.is_soulbound(token_id),
+                metadata_frozen: nft.is_metadata_frozen(token_id),
+            });
+            msg::reply(StateReply::TokenInfo(token_info), 0).expect("Unable to share the state");
         }
         StateQuery::OwnerTokens { owner } => {
             let tokens = nft
@@ -572,25 +2882,310 @@ extern "C" fn state() {
                     token_id: nft.token_id,
                     sale_active: sale_active_status,
                     total_supply: nft.token_metadata_by_id.len() as u128,
+                    paused: nft.paused,
+                    frozen: nft.frozen,
+                    metadata_frozen: nft.metadata_frozen,
                 }),
                 0,
             )
             .expect("Unable to share the state");
         }
+        StateQuery::ExportHeader => {
+            msg::reply(StateReply::ExportHeader(nft.export_header()), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::ExportRecords { offset, limit } => {
+            msg::reply(
+                StateReply::ExportRecords(nft.export_records(offset, limit)),
+                0,
+            )
+            .expect("Unable to share the state");
+        }
+        StateQuery::IsApprovedForAll { owner, operator } => {
+            msg::reply(
+                StateReply::IsApprovedForAll(nft.is_operator(&owner, &operator)),
+                0,
+            )
+            .expect("Unable to share the state");
+        }
+        StateQuery::BalanceOf { owner } => {
+            msg::reply(StateReply::BalanceOf(nft.balance_of(&owner)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::BalancesOf { owners } => {
+            let balances = owners
+                .into_iter()
+                .map(|owner| (owner, nft.balance_of(&owner)))
+                .collect();
+            msg::reply(StateReply::BalancesOf(balances), 0).expect("Unable to share the state");
+        }
+        StateQuery::TokenLock { token_id } => {
+            let lock = nft
+                .token_locks
+                .get(&token_id)
+                .filter(|lock| !is_expired(Some(lock.until)))
+                .cloned();
+            msg::reply(StateReply::TokenLock(lock), 0).expect("Unable to share the state");
+        }
+        StateQuery::RoyaltyInfo {
+            token_id,
+            sale_price,
+        } => {
+            msg::reply(
+                StateReply::RoyaltyInfo(nft.royalty_info(token_id, sale_price)),
+                0,
+            )
+            .expect("Unable to share the state");
+        }
+        StateQuery::Listings { offset, limit } => {
+            msg::reply(StateReply::Listings(nft.listings(offset, limit)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::BestOffer { token_id } => {
+            msg::reply(StateReply::BestOffer(nft.best_offer(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::Auction { token_id } => {
+            msg::reply(StateReply::Auction(nft.auctions.get(&token_id).cloned()), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::UserOf { token_id } => {
+            msg::reply(StateReply::UserOf(nft.active_user(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::DelegatesOf { owner } => {
+            msg::reply(StateReply::DelegatesOf(nft.delegates_of(&owner)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::IsDelegate {
+            owner,
+            delegate,
+            token_id,
+        } => {
+            msg::reply(
+                StateReply::IsDelegate(nft.is_delegate(&owner, &delegate, token_id)),
+                0,
+            )
+            .expect("Unable to share the state");
+        }
+        StateQuery::Children { token_id } => {
+            msg::reply(StateReply::Children(nft.children(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::RootOwner { token_id } => {
+            msg::reply(StateReply::RootOwner(nft.root_owner(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::StakeInfo { owner } => {
+            msg::reply(StateReply::StakeInfo(nft.stake_info(&owner)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::TokenAttributes { token_id } => {
+            let attributes = nft
+                .token_attributes
+                .get(&token_id)
+                .cloned()
+                .unwrap_or_default();
+            msg::reply(StateReply::TokenAttributes(attributes), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::TraitCounts { key } => {
+            msg::reply(StateReply::TraitCounts(nft.trait_counts(&key)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::TokenUri { token_id } => {
+            msg::reply(StateReply::TokenUri(nft.token_uri(token_id)), 0)
+                .expect("Unable to share the state");
+        }
+        StateQuery::Version => {
+            msg::reply(StateReply::Version(version_info()), 0).expect("Unable to share the state");
+        }
+    }
+}
+
+/// Checks that a royalty stays within `ROYALTY_BPS_MAX` and has a receiver
+fn check_royalty(receiver: &ActorId, bps: u16) {
+    if bps > ROYALTY_BPS_MAX {
+        panic!(
+            "NonFungibleToken: royalty {} bps exceeds {} bps",
+            bps, ROYALTY_BPS_MAX
+        );
+    }
+    if bps > 0 && *receiver == ZERO_ID {
+        panic!("NonFungibleToken: zero address");
+    }
+}
+
+/// Checks that the attributes of a token fit `TOKEN_ATTRIBUTES_MAX` and have distinct keys
+fn check_attributes(attributes: &[Attribute]) {
+    if attributes.len() > TOKEN_ATTRIBUTES_MAX {
+        panic!(
+            "NonFungibleToken: attribute limit {} exceeded",
+            TOKEN_ATTRIBUTES_MAX
+        );
+    }
+    for (i, attribute) in attributes.iter().enumerate() {
+        if attribute.key.is_empty() {
+            panic!("NonFungibleToken: empty attribute key");
+        }
+        if attributes[..i]
+            .iter()
+            .any(|previous| previous.key == attribute.key)
+        {
+            panic!("NonFungibleToken: duplicate attribute {}", attribute.key);
+        }
+    }
+}
+
+/// Checks the links, the category and the social links of the collection
+fn check_collection(collection: &Collection) {
+    check_link(&collection.banner);
+    check_link(&collection.logo);
+    check_link(&collection.external_link);
+    check_link(&collection.contract_uri);
+    if collection.category.len() > COLLECTION_FIELD_MAX_LEN {
+        panic!(
+            "NonFungibleToken: category exceeds {} bytes",
+            COLLECTION_FIELD_MAX_LEN
+        );
+    }
+    if collection.social_links.len() > SOCIAL_LINKS_MAX {
+        panic!(
+            "NonFungibleToken: social link limit {} exceeded",
+            SOCIAL_LINKS_MAX
+        );
+    }
+    for (i, link) in collection.social_links.iter().enumerate() {
+        if link.platform.is_empty() || link.url.is_empty() {
+            panic!("NonFungibleToken: empty social link");
+        }
+        if link.platform.len() > COLLECTION_FIELD_MAX_LEN {
+            panic!(
+                "NonFungibleToken: platform exceeds {} bytes",
+                COLLECTION_FIELD_MAX_LEN
+            );
+        }
+        check_link(&link.url);
+        if collection.social_links[..i]
+            .iter()
+            .any(|previous| previous.platform == link.platform)
+        {
+            panic!("NonFungibleToken: duplicate social link {}", link.platform);
+        }
+    }
+}
+
+/// Checks that a link is either empty or an URI with a scheme within `COLLECTION_FIELD_MAX_LEN`
+fn check_link(link: &str) {
+    if link.len() > COLLECTION_FIELD_MAX_LEN {
+        panic!(
+            "NonFungibleToken: link exceeds {} bytes",
+            COLLECTION_FIELD_MAX_LEN
+        );
+    }
+    if !link.is_empty() && !link.contains("://") {
+        panic!("NonFungibleToken: link {} has no scheme", link);
+    }
+}
+
+/// Checks that the zero-padding of token ids stays within `URI_PADDING_MAX`
+fn check_uri_padding(padding: u8) {
+    if padding > URI_PADDING_MAX {
+        panic!(
+            "NonFungibleToken: URI padding {} exceeds {}",
+            padding, URI_PADDING_MAX
+        );
+    }
+}
+
+/// Checks that the marketplace fee stays within `PLATFORM_FEE_BPS_MAX`
+fn check_platform_fee(bps: u16) {
+    if bps > PLATFORM_FEE_BPS_MAX {
+        panic!(
+            "NonFungibleToken: platform fee {} bps exceeds {} bps",
+            bps, PLATFORM_FEE_BPS_MAX
+        );
+    }
+}
+
+/// Sends `amount` of value to `to` unless it is zero
+fn send_value(to: &ActorId, amount: u128) {
+    if amount > 0 {
+        debug!("Sending {} to {:?}", amount, to);
+        msg::send(*to, NftEvent::TransferValue, amount).expect("Failed to send value!");
+    }
+}
+
+/// Share of `amount` in basis points
+fn bps_of(amount: u128, bps: u16) -> u128 {
+    let bps = u128::from(bps);
+
+    amount / 10_000 * bps + amount % 10_000 * bps / 10_000
+}
+
+/// Current block timestamp in seconds
+fn current_timestamp() -> u64 {
+    exec::block_timestamp() / 1000
+}
+
+fn is_expired(expires_at: Option<u64>) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= current_timestamp())
+}
+
+fn version_info() -> VersionInfo {
+    VersionInfo {
+        major: env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap_or_default(),
+        minor: env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or_default(),
+        patch: env!("CARGO_PKG_VERSION_PATCH").parse().unwrap_or_default(),
+        features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+        standards: STANDARDS
+            .iter()
+            .map(|standard| standard.to_string())
+            .collect(),
     }
 }
 
 impl From<Nft> for State {
     fn from(value: Nft) -> Self {
+        let stakes = value
+            .stakes
+            .keys()
+            .map(|owner| (*owner, value.stake_info(owner)))
+            .collect();
+
         let Nft {
             owner_by_id,
             token_approvals,
             token_metadata_by_id,
             tokens_for_owner,
+            balances,
+            soulbound,
+            operator_approvals,
+            delegations,
+            token_locks,
+            token_royalties,
+            listings,
+            offers,
+            auctions,
+            token_users,
+            rental_terms,
+            token_parents,
+            token_children: _,
+            stakes: _,
+            staking_index: _,
+            staking_updated_at: _,
+            token_attributes,
+            token_uris,
+            frozen_metadata,
+            metadata_frozen,
+            pending_transfers: _,
             token_id,
             owner,
             collection,
             config,
+            paused,
+            frozen,
+            import: _,
         } = value;
 
         let owner_by_id = owner_by_id.into_iter().collect();
@@ -604,15 +3199,67 @@ impl From<Nft> for State {
             .map(|(id, tokens)| (id, tokens.into_iter().collect()))
             .collect();
 
+        let balances = balances.into_iter().collect();
+
+        let soulbound = soulbound.into_iter().collect();
+
+        let operator_approvals = operator_approvals
+            .into_iter()
+            .map(|(owner, operators)| (owner, operators.into_iter().collect()))
+            .collect();
+
+        let delegations = delegations.into_iter().collect();
+
+        let token_locks = token_locks.into_iter().collect();
+
+        let token_royalties = token_royalties.into_iter().collect();
+
+        let listings = listings.into_iter().collect();
+
+        let offers = offers.into_values().collect();
+
+        let auctions = auctions.into_iter().collect();
+
+        let token_users = token_users.into_iter().collect();
+
+        let rental_terms = rental_terms.into_iter().collect();
+
+        let token_parents = token_parents.into_iter().collect();
+
+        let token_attributes = token_attributes.into_iter().collect();
+
+        let token_uris = token_uris.into_iter().collect();
+
+        let frozen_metadata = frozen_metadata.into_iter().collect();
+
         Self {
             owner_by_id,
             token_approvals,
             token_metadata_by_id,
             tokens_for_owner,
+            balances,
+            soulbound,
+            operator_approvals,
+            delegations,
+            token_locks,
+            token_royalties,
+            listings,
+            offers,
+            auctions,
+            token_users,
+            rental_terms,
+            token_parents,
+            stakes,
+            token_attributes,
+            token_uris,
+            frozen_metadata,
+            metadata_frozen,
             token_id,
             owner,
             collection,
             config,
+            paused,
+            frozen,
         }
     }
 }
@@ -14,15 +14,10 @@ fn mint_success() {
     let nft = sys.get_program(1);
 
     let res = mint(&nft, USERS[0], USERS[1].into());
-    let token_metadata = TokenMetadata {
-        name: "CryptoKitty".to_string(),
-        description: "Description".to_string(),
-        media: "http://".to_string(),
-        reference: "http://".to_string(),
-    };
-    let message = NftEvent::Minted {
+    let message = NftEvent::Airdropped {
         to: USERS[1].into(),
-        token_metadata,
+        amount: 1,
+        soulbound: false,
     }
     .encode();
     assert!(res.contains(&(USERS[0], message)));
@@ -43,6 +38,14 @@ fn mint_failures() {
         description: String::from("My token"),
         symbol: String::from("My token"),
         base_uri: String::from("https://mynft-test.com/"),
+        uri_suffix: String::new(),
+        uri_padding: 0,
+        banner: String::new(),
+        logo: String::new(),
+        external_link: String::new(),
+        social_links: vec![],
+        category: String::new(),
+        contract_uri: String::new(),
     };
 
     let init_nft = InitNft {
@@ -55,6 +58,16 @@ fn mint_failures() {
             withdraw_wallet: USERS[4].into(),
             mint_limit: 1,
             sale_time: 0,
+            transferable: true,
+            royalty_receiver: USERS[0].into(),
+            royalty_bps: 0,
+            royalty_enforced: false,
+            platform_fee_bps: 0,
+            nesting_collections: vec![],
+            staking_rate: 0,
+            reward_token: None,
+            metadata_manager: ActorId::zero(),
+            owner_metadata_updates: false,
         },
     };
 
@@ -148,11 +161,21 @@ fn approve_success() {
         owner: USERS[1].into(),
         approved_account: USERS[2].into(),
         token_id: 0,
+        expires_at: None,
     }
     .encode();
     assert!(res.contains(&(USERS[1], message)));
     let state = get_state(&nft).expect("Unexpected invalid state.");
-    assert_eq!(state.token_approvals, [(0_u128, USERS[2].into())]);
+    assert_eq!(
+        state.token_approvals,
+        [(
+            0_u128,
+            Approval {
+                account: USERS[2].into(),
+                expires_at: None
+            }
+        )]
+    );
 
     assert!(!transfer(&nft, USERS[2], USERS[0], 0).main_failed());
 
@@ -649,4 +672,2306 @@ fn test_read_token_metadata() {
     let _res = get_token_meta(&nft, 1).expect("Unexpected invalid state.");
     assert!(_res.reference == String::from("https://mynft-test.com/1"));    
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_pause() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+
+    // Not authorized test
+    let res = nft.send(
+        USERS[1],
+        NftAction::Pause {
+            scope: PauseScope::Transfer,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::Pause {
+            scope: PauseScope::Transfer,
+        },
+    );
+    let message = NftEvent::Paused {
+        scope: PauseScope::Transfer,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    // must fail since transfers are paused
+    assert!(transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    // other scopes are still available
+    assert!(!approve(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert!(!burn(&nft, USERS[1], 1).main_failed());
+    // must fail since offers commit value to a transfer
+    sys.mint_to(USERS[2], 100_000_000_000_000);
+    let res = nft.send_with_value(
+        USERS[2],
+        NftAction::MakeOffer {
+            token_id: Some(0),
+            expires_at: None,
+        },
+        1_000_000_000_000,
+    );
+    assert!(res.main_failed());
+    // revocations are never paused
+    assert!(!nft
+        .send(USERS[1], NftAction::RevokeApproval { token_id: 0 })
+        .main_failed());
+
+    let program_info = get_program_info(&nft).expect("Unexpected invalid state.");
+    assert!(program_info.paused.transfer);
+    assert!(!program_info.paused.mint);
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::Unpause {
+            scope: PauseScope::Transfer,
+        },
+    );
+    let message = NftEvent::Unpaused {
+        scope: PauseScope::Transfer,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert!(!transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+
+    // pausing everything halts minting too
+    assert!(!nft
+        .send(
+            USERS[0],
+            NftAction::Pause {
+                scope: PauseScope::All
+            }
+        )
+        .main_failed());
+    assert!(buy(&nft, USERS[1], 1).main_failed());
+}
+
+#[test]
+fn test_migration() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    init_nft_airdrop(&sys);
+    let old_nft = sys.get_program(1);
+    let new_nft = sys.get_program(2);
+
+    assert!(!buy(&old_nft, USERS[1], 3).main_failed());
+    assert!(!approve(&old_nft, USERS[1], USERS[2], 1).main_failed());
+    let res = old_nft.send(
+        USERS[1],
+        NftAction::Approve {
+            to: USERS[3].into(),
+            token_id: 0,
+            expires_at: Some(sys.block_timestamp() / 1000 + 10),
+        },
+    );
+    assert!(!res.main_failed());
+    let res = old_nft.send(
+        USERS[1],
+        NftAction::Lock {
+            token_id: 2,
+            until: sys.block_timestamp() / 1000 + 1_000,
+            locker: USERS[3].into(),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // must fail since only the collection owner can freeze
+    assert!(old_nft.send(USERS[1], NftAction::Freeze {}).main_failed());
+    let res = old_nft.send(USERS[0], NftAction::Freeze {});
+    assert!(res.contains(&(USERS[0], NftEvent::Frozen.encode())));
+    // must fail since the program is frozen
+    assert!(transfer(&old_nft, USERS[1], USERS[2], 0).main_failed());
+
+    let StateReply::ExportHeader(header) = old_nft
+        .read_state(StateQuery::ExportHeader)
+        .expect("Unexpected invalid reply.")
+    else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(header.records, 3);
+
+    // checking an expired approval doesn't change the exported state
+    sys.spend_blocks(100);
+    assert!(!is_approved_to(&old_nft, USERS[0], 0, USERS[3]).main_failed());
+
+    let res = new_nft.send(USERS[0], NftAction::BeginImport { header });
+    assert!(!res.main_failed());
+
+    let mut offset = 0;
+    loop {
+        let StateReply::ExportRecords(records) = old_nft
+            .read_state(StateQuery::ExportRecords { offset, limit: 2 })
+            .expect("Unexpected invalid reply.")
+        else {
+            panic!("Unexpected invalid reply.");
+        };
+        if records.is_empty() {
+            break;
+        }
+        offset += records.len() as u64;
+        let res = new_nft.send(USERS[0], NftAction::ImportRecords { records });
+        assert!(!res.main_failed());
+    }
+
+    let res = new_nft.send(USERS[0], NftAction::FinishImport {});
+    assert!(!res.main_failed());
+
+    let old_state = get_state(&old_nft).expect("Unexpected invalid state.");
+    let new_state = get_state(&new_nft).expect("Unexpected invalid state.");
+    assert!(!new_state.frozen);
+    assert_eq!(new_state.token_id, old_state.token_id);
+    assert_eq!(new_state.token_approvals.len(), 2);
+    assert!(new_state.token_approvals.contains(&(
+        1,
+        Approval {
+            account: USERS[2].into(),
+            expires_at: None
+        }
+    )));
+    assert_eq!(new_state.owner_by_id.len(), 3);
+    // locks keep holding the migrated tokens
+    assert_eq!(new_state.token_locks, old_state.token_locks);
+    assert!(transfer(&new_nft, USERS[1], USERS[0], 2).main_failed());
+
+    // the migrated collection is fully functional
+    assert!(!transfer(&new_nft, USERS[2], USERS[0], 1).main_failed());
+}
+
+#[test]
+fn test_migration_checksum_mismatch() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+
+    let header = MigrationHeader {
+        owner: USERS[0].into(),
+        records: 1,
+        checksum: 42,
+        ..Default::default()
+    };
+    assert!(!nft
+        .send(USERS[0], NftAction::BeginImport { header })
+        .main_failed());
+
+    let records = vec![MigrationRecord::Token(TokenRecord {
+        token_id: 0,
+        owner: USERS[1].into(),
+        approval: None,
+        metadata: TokenMetadata::default(),
+        soulbound: false,
+        lock: None,
+        royalty: None,
+        listing: None,
+        user: None,
+        rental_terms: None,
+        parent: None,
+        attributes: Vec::new(),
+        uri: None,
+        metadata_frozen: false,
+    })];
+    assert!(!nft
+        .send(USERS[0], NftAction::ImportRecords { records })
+        .main_failed());
+
+    // must fail since the checksum doesn't match
+    assert!(nft.send(USERS[0], NftAction::FinishImport {}).main_failed());
+    // must fail since the program stays frozen until the import is finished
+    assert!(buy(&nft, USERS[1], 1).main_failed());
+    assert!(nft.send(USERS[0], NftAction::Unfreeze {}).main_failed());
+}
+
+#[test]
+fn test_version() {
+    let sys = System::new();
+    init_nft(&sys);
+    let nft = sys.get_program(1);
+
+    let reply = nft
+        .read_state(StateQuery::Version)
+        .expect("Unexpected invalid reply.");
+    let StateReply::Version(version) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(
+        format!("{}.{}.{}", version.major, version.minor, version.patch),
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(version.features.contains(&"pause".to_string()));
+    assert!(version.standards.contains(&"gnft-721".to_string()));
+}
+
+#[test]
+fn test_set_approval_for_all() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    // must fail since approval to the zero address
+    assert!(set_approval_for_all(&nft, USERS[1], ZERO_ID, true).main_failed());
+    // must fail since approval to the caller
+    assert!(set_approval_for_all(&nft, USERS[1], USERS[1], true).main_failed());
+
+    let res = set_approval_for_all(&nft, USERS[1], USERS[2], true);
+    let message = NftEvent::ApprovalForAll {
+        owner: USERS[1].into(),
+        operator: USERS[2].into(),
+        approved: true,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert!(!set_approval_for_all(&nft, USERS[1], USERS[3], true).main_failed());
+
+    let reply = nft
+        .read_state(StateQuery::IsApprovedForAll {
+            owner: USERS[1].into(),
+            operator: USERS[2].into(),
+        })
+        .expect("Unexpected invalid reply.");
+    assert!(matches!(reply, StateReply::IsApprovedForAll(true)));
+
+    // both operators can manage any token of the owner
+    assert!(!transfer(&nft, USERS[2], USERS[0], 0).main_failed());
+    assert!(!burn(&nft, USERS[3], 1).main_failed());
+
+    // revoke
+    assert!(!set_approval_for_all(&nft, USERS[1], USERS[2], false).main_failed());
+    assert!(transfer(&nft, USERS[2], USERS[0], 2).main_failed());
+    assert!(!transfer(&nft, USERS[3], USERS[0], 2).main_failed());
+}
+
+#[test]
+fn test_replace_and_revoke_approval() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+
+    assert!(!approve(&nft, USERS[1], USERS[2], 0).main_failed());
+    // a new approval replaces the previous one
+    assert!(!approve(&nft, USERS[1], USERS[3], 0).main_failed());
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert_eq!(
+        state.token_approvals,
+        [(
+            0_u128,
+            Approval {
+                account: USERS[3].into(),
+                expires_at: None
+            }
+        )]
+    );
+
+    // must fail since the caller is not the token owner
+    assert!(nft
+        .send(USERS[3], NftAction::RevokeApproval { token_id: 0 })
+        .main_failed());
+
+    let res = nft.send(USERS[1], NftAction::RevokeApproval { token_id: 0 });
+    let message = NftEvent::ApprovalRevoked {
+        owner: USERS[1].into(),
+        revoked_account: USERS[3].into(),
+        token_id: 0,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    // must fail since the approval was revoked
+    assert!(transfer(&nft, USERS[3], USERS[0], 0).main_failed());
+    // must fail since there is nothing to revoke
+    assert!(nft
+        .send(USERS[1], NftAction::RevokeApproval { token_id: 0 })
+        .main_failed());
+}
+
+#[test]
+fn test_approval_expiry() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+
+    let now = sys.block_timestamp() / 1000;
+
+    // must fail since the expiry is in the past
+    let res = nft.send(
+        USERS[1],
+        NftAction::Approve {
+            to: USERS[2].into(),
+            token_id: 0,
+            expires_at: Some(now),
+        },
+    );
+    assert!(res.main_failed());
+
+    let expires_at = Some(now + 10);
+    let res = nft.send(
+        USERS[1],
+        NftAction::Approve {
+            to: USERS[2].into(),
+            token_id: 0,
+            expires_at,
+        },
+    );
+    let message = NftEvent::Approved {
+        owner: USERS[1].into(),
+        approved_account: USERS[2].into(),
+        token_id: 0,
+        expires_at,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    let reply = nft
+        .read_state(StateQuery::TokenApprovals { token_id: 0 })
+        .expect("Unexpected invalid reply.");
+    let StateReply::TokenApprovals(Some(approval)) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(approval.expires_at, expires_at);
+
+    sys.spend_blocks(100);
+
+    let reply = nft
+        .read_state(StateQuery::TokenApprovals { token_id: 0 })
+        .expect("Unexpected invalid reply.");
+    assert!(matches!(reply, StateReply::TokenApprovals(None)));
+
+    // must fail since the approval has expired
+    assert!(transfer(&nft, USERS[2], USERS[0], 0).main_failed());
+
+    let res = is_approved_to(&nft, USERS[0], 0, USERS[2]);
+    let message = NftEvent::CheckIfApproved {
+        to: USERS[2].into(),
+        token_id: 0,
+        approved: false,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    // the check is read-only, the expired approval is just ignored
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert_eq!(state.token_approvals.len(), 1);
+}
+
+#[test]
+fn test_batch_transfer() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    // must fail as a whole since the caller doesn't own the last token
+    assert!(!buy(&nft, USERS[2], 1).main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::BatchTransfer {
+            transfers: vec![(USERS[0].into(), 0), (USERS[0].into(), 3)],
+        },
+    );
+    assert!(res.main_failed());
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert!(state.owner_by_id.contains(&(0, USERS[1].into())));
+
+    let res = nft.send(
+        USERS[1],
+        NftAction::BatchTransfer {
+            transfers: vec![
+                (USERS[0].into(), 0),
+                (USERS[0].into(), 1),
+                (USERS[3].into(), 2),
+            ],
+        },
+    );
+    let message = NftEvent::BatchTransferred {
+        transfers: vec![
+            (USERS[1].into(), USERS[0].into(), 0),
+            (USERS[1].into(), USERS[0].into(), 1),
+            (USERS[1].into(), USERS[3].into(), 2),
+        ],
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert!(!state
+        .tokens_for_owner
+        .iter()
+        .any(|(owner, _)| *owner == USERS[1].into()));
+}
+
+#[test]
+fn test_safe_transfer() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    let accepting: u64 = 100;
+    let rejecting: u64 = 101;
+    gtest::Program::mock_with_id(&sys, accepting, NftReceiver { accept: true });
+    gtest::Program::mock_with_id(&sys, rejecting, NftReceiver { accept: false });
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    let res = safe_transfer(&nft, USERS[1], accepting, 0, true);
+    let message = NftEvent::Transferred {
+        from: USERS[1].into(),
+        to: accepting.into(),
+        token_id: 0,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    let res = safe_transfer(&nft, USERS[1], rejecting, 1, true);
+    let message = NftEvent::SafeTransferRejected {
+        from: USERS[1].into(),
+        to: rejecting.into(),
+        token_id: 1,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    // an account receives the token right away
+    let res = safe_transfer(&nft, USERS[1], USERS[2], 2, false);
+    let message = NftEvent::Transferred {
+        from: USERS[1].into(),
+        to: USERS[2].into(),
+        token_id: 2,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert!(state.owner_by_id.contains(&(0, accepting.into())));
+    assert!(state.owner_by_id.contains(&(1, USERS[1].into())));
+    assert!(state.owner_by_id.contains(&(2, USERS[2].into())));
+}
+
+#[test]
+fn test_safe_transfer_timeout() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+
+    // an account stated to be a program never replies, so the transfer waits for it
+    assert!(!safe_transfer(&nft, USERS[1], USERS[2], 0, true).main_failed());
+    // must fail since the token transfer is pending
+    assert!(transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+
+    sys.spend_blocks(101);
+
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert!(state.owner_by_id.contains(&(0, USERS[1].into())));
+    assert!(!transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+}
+
+#[test]
+fn test_balance_of() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+    assert!(!transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert!(!burn(&nft, USERS[1], 1).main_failed());
+    // transfer to the owner keeps the balance intact
+    assert!(!transfer(&nft, USERS[1], USERS[1], 2).main_failed());
+
+    let reply = nft
+        .read_state(StateQuery::BalanceOf {
+            owner: USERS[1].into(),
+        })
+        .expect("Unexpected invalid reply.");
+    assert!(matches!(reply, StateReply::BalanceOf(1)));
+
+    let reply = nft
+        .read_state(StateQuery::BalancesOf {
+            owners: vec![USERS[1].into(), USERS[2].into(), USERS[3].into()],
+        })
+        .expect("Unexpected invalid reply.");
+    let StateReply::BalancesOf(balances) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(
+        balances,
+        [
+            (USERS[1].into(), 1),
+            (USERS[2].into(), 1),
+            (USERS[3].into(), 0)
+        ]
+    );
+}
+
+#[test]
+fn test_soulbound() {
+    // collections built from the default config stay transferable
+    assert!(Config::default().transferable);
+
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+
+    // must fail since only the collection owner can airdrop
+    let res = nft.send(
+        USERS[1],
+        NftAction::Airdrop {
+            to: USERS[1].into(),
+            amount: 1,
+            soulbound: true,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::Airdrop {
+            to: USERS[1].into(),
+            amount: 1,
+            soulbound: true,
+        },
+    );
+    let message = NftEvent::Airdropped {
+        to: USERS[1].into(),
+        amount: 1,
+        soulbound: true,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    let reply = nft
+        .read_state(StateQuery::TokenInfo { token_id: 1 })
+        .expect("Unexpected invalid reply.");
+    let StateReply::TokenInfo(Some(token_info)) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert!(token_info.soulbound);
+
+    // must fail since the token is soulbound
+    assert!(transfer(&nft, USERS[1], USERS[2], 1).main_failed());
+    assert!(approve(&nft, USERS[1], USERS[2], 1).main_failed());
+    assert!(!set_approval_for_all(&nft, USERS[1], USERS[2], true).main_failed());
+    assert!(transfer(&nft, USERS[2], USERS[0], 1).main_failed());
+    assert!(burn(&nft, USERS[2], 1).main_failed());
+
+    // regular tokens stay transferable
+    assert!(!transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    // the owner can still burn a soulbound token
+    assert!(!burn(&nft, USERS[1], 1).main_failed());
+}
+
+#[test]
+fn test_lock() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+
+    let until = sys.block_timestamp() / 1000 + 10;
+
+    // must fail since the caller is not the token owner
+    let res = nft.send(
+        USERS[2],
+        NftAction::Lock {
+            token_id: 0,
+            until,
+            locker: USERS[2].into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since approved accounts and operators can't lock the token
+    assert!(!approve(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert!(!set_approval_for_all(&nft, USERS[1], USERS[3], true).main_failed());
+    for account in [USERS[2], USERS[3]] {
+        let res = nft.send(
+            account,
+            NftAction::Lock {
+                token_id: 0,
+                until,
+                locker: account.into(),
+            },
+        );
+        assert!(res.main_failed());
+    }
+    assert!(!nft
+        .send(USERS[1], NftAction::RevokeApproval { token_id: 0 })
+        .main_failed());
+
+    let res = nft.send(
+        USERS[1],
+        NftAction::Lock {
+            token_id: 0,
+            until,
+            locker: USERS[2].into(),
+        },
+    );
+    let message = NftEvent::Locked {
+        token_id: 0,
+        locker: USERS[2].into(),
+        until,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    let reply = nft
+        .read_state(StateQuery::TokenLock { token_id: 0 })
+        .expect("Unexpected invalid reply.");
+    let StateReply::TokenLock(Some(lock)) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(lock.locker, USERS[2].into());
+
+    // must fail since the token is locked
+    assert!(transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+    assert!(approve(&nft, USERS[1], USERS[0], 0).main_failed());
+    assert!(burn(&nft, USERS[1], 0).main_failed());
+    // must fail since only the locker can unlock
+    assert!(nft
+        .send(USERS[1], NftAction::Unlock { token_id: 0 })
+        .main_failed());
+
+    let res = nft.send(USERS[2], NftAction::Unlock { token_id: 0 });
+    let message = NftEvent::Unlocked { token_id: 0 }.encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert!(!transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+}
+
+#[test]
+fn test_lock_expiry() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+
+    let res = nft.send(
+        USERS[1],
+        NftAction::Lock {
+            token_id: 0,
+            until: sys.block_timestamp() / 1000 + 10,
+            locker: USERS[2].into(),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+
+    sys.spend_blocks(100);
+
+    let reply = nft
+        .read_state(StateQuery::TokenLock { token_id: 0 })
+        .expect("Unexpected invalid reply.");
+    assert!(matches!(reply, StateReply::TokenLock(None)));
+    assert!(!transfer(&nft, USERS[1], USERS[0], 0).main_failed());
+}
+
+#[test]
+fn test_royalty() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+
+    // must fail since the royalty exceeds the maximum
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetDefaultRoyalty {
+            receiver: USERS[0].into(),
+            bps: ROYALTY_BPS_MAX + 1,
+        },
+    );
+    assert!(res.main_failed());
+    // Not authorized test
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetDefaultRoyalty {
+            receiver: USERS[1].into(),
+            bps: 500,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetDefaultRoyalty {
+            receiver: USERS[0].into(),
+            bps: 500,
+        },
+    );
+    let message = NftEvent::DefaultRoyaltyChanged {
+        receiver: USERS[0].into(),
+        bps: 500,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    let royalty = Some(Royalty {
+        receiver: USERS[3].into(),
+        bps: 250,
+    });
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenRoyalty {
+            token_id: 1,
+            royalty,
+        },
+    );
+    let message = NftEvent::TokenRoyaltyChanged {
+        token_id: 1,
+        royalty,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    let royalty_info = |token_id| {
+        let reply = nft
+            .read_state(StateQuery::RoyaltyInfo {
+                token_id,
+                sale_price: 10_000,
+            })
+            .expect("Unexpected invalid reply.");
+        let StateReply::RoyaltyInfo(royalty_info) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        royalty_info
+    };
+    assert_eq!(
+        royalty_info(0),
+        Some(RoyaltyInfo {
+            receiver: USERS[0].into(),
+            amount: 500
+        })
+    );
+    assert_eq!(
+        royalty_info(1),
+        Some(RoyaltyInfo {
+            receiver: USERS[3].into(),
+            amount: 250
+        })
+    );
+    assert_eq!(royalty_info(2), None);
+}
+
+#[test]
+fn test_sale_transfer() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+    sys.mint_to(USERS[2], 100_000_000_000_000);
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetDefaultRoyalty {
+            receiver: USERS[0].into(),
+            bps: 500,
+        },
+    );
+    assert!(!res.main_failed());
+    // Not authorized test
+    let res = nft.send(USERS[1], NftAction::SetRoyaltyEnforced { enforced: true });
+    assert!(res.main_failed());
+
+    let res = nft.send(USERS[0], NftAction::SetRoyaltyEnforced { enforced: true });
+    let message = NftEvent::RoyaltyEnforcedChanged { enforced: true }.encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert!(!approve(&nft, USERS[1], USERS[2], 0).main_failed());
+
+    // must fail since approved accounts have to pay the royalty
+    assert!(transfer(&nft, USERS[2], USERS[3], 0).main_failed());
+    // must fail since a free sale would skip the royalty
+    let res = nft.send(
+        USERS[2],
+        NftAction::SaleTransfer {
+            to: USERS[3].into(),
+            token_id: 0,
+            price: 0,
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the attached value is below the price
+    let price = 10_000_000_000_000;
+    let res = nft.send_with_value(
+        USERS[2],
+        NftAction::SaleTransfer {
+            to: USERS[3].into(),
+            token_id: 0,
+            price,
+        },
+        price - 1,
+    );
+    assert!(res.main_failed());
+
+    let seller_balance = sys.balance_of(USERS[1]);
+    let receiver_balance = sys.balance_of(USERS[0]);
+    let res = nft.send_with_value(
+        USERS[2],
+        NftAction::SaleTransfer {
+            to: USERS[3].into(),
+            token_id: 0,
+            price,
+        },
+        price,
+    );
+    let message = NftEvent::SaleTransferred {
+        from: USERS[1].into(),
+        to: USERS[3].into(),
+        token_id: 0,
+        price,
+        royalty: 500_000_000_000,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert_eq!(sys.balance_of(USERS[1]), seller_balance + 9_500_000_000_000);
+    assert_eq!(sys.balance_of(USERS[0]), receiver_balance + 500_000_000_000);
+
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, USERS[3].into())));
+
+    // the owner can still transfer without a sale
+    assert!(!transfer(&nft, USERS[3], USERS[4], 0).main_failed());
+}
+
+#[test]
+fn test_listing() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+    sys.mint_to(USERS[2], 100_000_000_000_000);
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetDefaultRoyalty {
+            receiver: USERS[0].into(),
+            bps: 500,
+        },
+    );
+    assert!(!res.main_failed());
+    // must fail since the fee exceeds the maximum
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetPlatformFee {
+            bps: PLATFORM_FEE_BPS_MAX + 1,
+        },
+    );
+    assert!(res.main_failed());
+    let res = nft.send(USERS[0], NftAction::SetPlatformFee { bps: 250 });
+    let message = NftEvent::PlatformFeeChanged { bps: 250 }.encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    let price = 10_000_000_000_000;
+    // Not authorized test
+    let res = nft.send(
+        USERS[2],
+        NftAction::List {
+            token_id: 0,
+            price,
+            expires_at: None,
+        },
+    );
+    assert!(res.main_failed());
+    for token_id in 0..2 {
+        let res = nft.send(
+            USERS[1],
+            NftAction::List {
+                token_id,
+                price,
+                expires_at: None,
+            },
+        );
+        let message = NftEvent::Listed {
+            seller: USERS[1].into(),
+            token_id,
+            price,
+            expires_at: None,
+        }
+        .encode();
+        assert!(res.contains(&(USERS[1], message)));
+    }
+
+    let listings = |offset, limit| {
+        let reply = nft
+            .read_state(StateQuery::Listings { offset, limit })
+            .expect("Unexpected invalid reply.");
+        let StateReply::Listings(listings) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        listings
+    };
+    assert_eq!(listings(0, 10).len(), 2);
+    assert_eq!(listings(1, 10)[0].0, 1);
+
+    // must fail since the attached value is below the price
+    let res = nft.send_with_value(USERS[2], NftAction::BuyListed { token_id: 0 }, price - 1);
+    assert!(res.main_failed());
+
+    let seller_balance = sys.balance_of(USERS[1]);
+    let receiver_balance = sys.balance_of(USERS[0]);
+    let dev_balance = sys.balance_of(USERS[3]);
+    let res = nft.send_with_value(USERS[2], NftAction::BuyListed { token_id: 0 }, price);
+    let message = NftEvent::ListingSold {
+        seller: USERS[1].into(),
+        buyer: USERS[2].into(),
+        token_id: 0,
+        price,
+        royalty: 500_000_000_000,
+        fee: 250_000_000_000,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert_eq!(sys.balance_of(USERS[1]), seller_balance + 9_250_000_000_000);
+    assert_eq!(sys.balance_of(USERS[0]), receiver_balance + 500_000_000_000);
+    assert_eq!(sys.balance_of(USERS[3]), dev_balance + 250_000_000_000);
+
+    // the sold listing is closed and so is the listing of a transferred token
+    assert!(!transfer(&nft, USERS[1], USERS[4], 1).main_failed());
+    assert!(listings(0, 10).is_empty());
+    let res = nft.send_with_value(USERS[2], NftAction::BuyListed { token_id: 1 }, price);
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[4],
+        NftAction::List {
+            token_id: 1,
+            price,
+            expires_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = nft.send(USERS[4], NftAction::Delist { token_id: 1 });
+    let message = NftEvent::Delisted { token_id: 1 }.encode();
+    assert!(res.contains(&(USERS[4], message)));
+    assert!(listings(0, 10).is_empty());
+
+    // a listing lapses once its lister loses the approval
+    let list = |from| {
+        nft.send(
+            from,
+            NftAction::List {
+                token_id: 1,
+                price,
+                expires_at: None,
+            },
+        )
+    };
+    assert!(!approve(&nft, USERS[4], USERS[3], 1).main_failed());
+    assert!(!list(USERS[3]).main_failed());
+    assert_eq!(listings(0, 10)[0].1.lister, USERS[3].into());
+    assert!(!nft
+        .send(USERS[4], NftAction::RevokeApproval { token_id: 1 })
+        .main_failed());
+    assert!(listings(0, 10).is_empty());
+    let res = nft.send_with_value(USERS[2], NftAction::BuyListed { token_id: 1 }, price);
+    assert!(res.main_failed());
+
+    // and so does the listing of a revoked operator
+    assert!(!set_approval_for_all(&nft, USERS[4], USERS[3], true).main_failed());
+    assert!(!list(USERS[3]).main_failed());
+    assert!(!set_approval_for_all(&nft, USERS[4], USERS[3], false).main_failed());
+    assert!(listings(0, 10).is_empty());
+    let res = nft.send_with_value(USERS[2], NftAction::BuyListed { token_id: 1 }, price);
+    assert!(res.main_failed());
+}
+
+#[test]
+fn test_offers() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+    sys.mint_to(USERS[2], 100_000_000_000_000);
+    sys.mint_to(USERS[4], 100_000_000_000_000);
+
+    let best_offer = |token_id| {
+        let reply = nft
+            .read_state(StateQuery::BestOffer { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::BestOffer(offer) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        offer
+    };
+
+    // must fail since the owner can't bid on its own token
+    let res = nft.send_with_value(
+        USERS[1],
+        NftAction::MakeOffer {
+            token_id: Some(0),
+            expires_at: None,
+        },
+        1_000_000_000_000,
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send_with_value(
+        USERS[2],
+        NftAction::MakeOffer {
+            token_id: Some(0),
+            expires_at: None,
+        },
+        2_000_000_000_000,
+    );
+    let message = NftEvent::OfferMade {
+        buyer: USERS[2].into(),
+        token_id: Some(0),
+        amount: 2_000_000_000_000,
+        expires_at: None,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    let res = nft.send_with_value(
+        USERS[4],
+        NftAction::MakeOffer {
+            token_id: Some(0),
+            expires_at: None,
+        },
+        3_000_000_000_000,
+    );
+    assert!(!res.main_failed());
+    let res = nft.send_with_value(
+        USERS[4],
+        NftAction::MakeOffer {
+            token_id: None,
+            expires_at: None,
+        },
+        1_000_000_000_000,
+    );
+    assert!(!res.main_failed());
+
+    assert_eq!(
+        best_offer(Some(0)),
+        Some(Offer {
+            buyer: USERS[4].into(),
+            token_id: Some(0),
+            amount: 3_000_000_000_000,
+            expires_at: None,
+        })
+    );
+    assert_eq!(
+        best_offer(None).map(|offer| offer.amount),
+        Some(1_000_000_000_000)
+    );
+    assert_eq!(best_offer(Some(1)), None);
+
+    // Cancel with refund
+    let buyer_balance = sys.balance_of(USERS[2]);
+    let res = nft.send(USERS[2], NftAction::CancelOffer { token_id: Some(0) });
+    let message = NftEvent::OfferCancelled {
+        buyer: USERS[2].into(),
+        token_id: Some(0),
+        refund: 2_000_000_000_000,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert_eq!(sys.balance_of(USERS[2]), buyer_balance + 2_000_000_000_000);
+
+    // Not authorized test
+    let res = nft.send(
+        USERS[2],
+        NftAction::AcceptOffer {
+            token_id: 0,
+            buyer: USERS[4].into(),
+            collection_offer: false,
+        },
+    );
+    assert!(res.main_failed());
+
+    let seller_balance = sys.balance_of(USERS[1]);
+    let res = nft.send(
+        USERS[1],
+        NftAction::AcceptOffer {
+            token_id: 1,
+            buyer: USERS[4].into(),
+            collection_offer: true,
+        },
+    );
+    let message = NftEvent::OfferAccepted {
+        seller: USERS[1].into(),
+        buyer: USERS[4].into(),
+        token_id: 1,
+        price: 1_000_000_000_000,
+        royalty: 0,
+        fee: 0,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert_eq!(sys.balance_of(USERS[1]), seller_balance + 1_000_000_000_000);
+    assert_eq!(best_offer(None), None);
+
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(1, USERS[4].into())));
+    // must fail since the collection offer was used
+    let res = nft.send(
+        USERS[1],
+        NftAction::AcceptOffer {
+            token_id: 0,
+            buyer: USERS[4].into(),
+            collection_offer: true,
+        },
+    );
+    assert!(res.main_failed());
+
+    // freezing for a migration refunds the open offers
+    let buyer_balance = sys.balance_of(USERS[4]);
+    assert!(!nft.send(USERS[0], NftAction::Freeze {}).main_failed());
+    assert_eq!(sys.balance_of(USERS[4]), buyer_balance + 3_000_000_000_000);
+    assert_eq!(best_offer(Some(0)), None);
+}
+
+#[test]
+fn test_auction() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+    sys.mint_to(USERS[2], 100_000_000_000_000);
+    sys.mint_to(USERS[4], 100_000_000_000_000);
+
+    let auction = || {
+        let reply = nft
+            .read_state(StateQuery::Auction { token_id: 0 })
+            .expect("Unexpected invalid reply.");
+        let StateReply::Auction(auction) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        auction
+    };
+
+    let ends_at = sys.block_timestamp() / 1000 + 100;
+    // Not authorized test
+    let start = NftAction::StartAuction {
+        token_id: 0,
+        reserve_price: 2_000_000_000_000,
+        min_increment: 1_000_000_000_000,
+        ends_at,
+        extension: 50,
+    };
+    assert!(nft.send(USERS[2], start).main_failed());
+    let start = NftAction::StartAuction {
+        token_id: 0,
+        reserve_price: 2_000_000_000_000,
+        min_increment: 1_000_000_000_000,
+        ends_at,
+        extension: 50,
+    };
+    let res = nft.send(USERS[1], start);
+    let message = NftEvent::AuctionStarted {
+        seller: USERS[1].into(),
+        token_id: 0,
+        reserve_price: 2_000_000_000_000,
+        min_increment: 1_000_000_000_000,
+        ends_at,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    // must fail since the token is auctioned
+    assert!(transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert!(burn(&nft, USERS[1], 0).main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::Lock {
+            token_id: 0,
+            until: u64::MAX,
+            locker: USERS[1].into(),
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the bid is below the reserve price
+    let res = nft.send_with_value(USERS[2], NftAction::Bid { token_id: 0 }, 1_000_000_000_000);
+    assert!(res.main_failed());
+
+    let res = nft.send_with_value(USERS[2], NftAction::Bid { token_id: 0 }, 2_000_000_000_000);
+    assert!(!res.main_failed());
+    // must fail since the bid doesn't exceed the highest one by the increment
+    let res = nft.send_with_value(USERS[4], NftAction::Bid { token_id: 0 }, 2_500_000_000_000);
+    assert!(res.main_failed());
+    // must fail since the auction has bids
+    let res = nft.send(USERS[1], NftAction::CancelAuction { token_id: 0 });
+    assert!(res.main_failed());
+
+    // a late bid extends the auction and refunds the outbid bidder
+    sys.spend_blocks(80);
+    let bidder_balance = sys.balance_of(USERS[2]);
+    let res = nft.send_with_value(USERS[4], NftAction::Bid { token_id: 0 }, 4_000_000_000_000);
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), bidder_balance + 2_000_000_000_000);
+    let extended = auction().expect("Unexpected invalid auction.");
+    assert!(extended.ends_at > ends_at);
+    assert_eq!(
+        extended.highest_bid,
+        Some(Bid {
+            bidder: USERS[4].into(),
+            amount: 4_000_000_000_000,
+        })
+    );
+
+    // must fail since the auction is live
+    let res = nft.send(USERS[2], NftAction::SettleAuction { token_id: 0 });
+    assert!(res.main_failed());
+
+    sys.spend_blocks(100);
+    let seller_balance = sys.balance_of(USERS[1]);
+    let res = nft.send(USERS[2], NftAction::SettleAuction { token_id: 0 });
+    let message = NftEvent::AuctionSettled {
+        seller: USERS[1].into(),
+        winner: Some(USERS[4].into()),
+        token_id: 0,
+        price: 4_000_000_000_000,
+        royalty: 0,
+        fee: 0,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert_eq!(sys.balance_of(USERS[1]), seller_balance + 4_000_000_000_000);
+    assert_eq!(auction(), None);
+
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, USERS[4].into())));
+
+    // an endless auction with a bid doesn't block the migration
+    let start = NftAction::StartAuction {
+        token_id: 0,
+        reserve_price: 1,
+        min_increment: 0,
+        ends_at: u64::MAX,
+        extension: 0,
+    };
+    assert!(!nft.send(USERS[4], start).main_failed());
+    let res = nft.send_with_value(USERS[2], NftAction::Bid { token_id: 0 }, 1_000_000_000_000);
+    assert!(!res.main_failed());
+    let bidder_balance = sys.balance_of(USERS[2]);
+    assert!(!nft.send(USERS[0], NftAction::Freeze {}).main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), bidder_balance + 1_000_000_000_000);
+    assert_eq!(auction(), None);
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, USERS[4].into())));
+}
+
+#[test]
+fn test_rental() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+    sys.mint_to(USERS[4], 100_000_000_000_000);
+
+    let user_of = |token_id| {
+        let reply = nft
+            .read_state(StateQuery::UserOf { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::UserOf(user) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        user
+    };
+
+    let expires = sys.block_timestamp() / 1000 + 10;
+    // Not authorized test
+    let res = nft.send(
+        USERS[2],
+        NftAction::SetUser {
+            token_id: 0,
+            user: USERS[2].into(),
+            expires,
+        },
+    );
+    assert!(res.main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetUser {
+            token_id: 0,
+            user: USERS[2].into(),
+            expires,
+        },
+    );
+    let message = NftEvent::UserUpdated {
+        token_id: 0,
+        user: USERS[2].into(),
+        expires,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert_eq!(user_of(0).map(|user| user.user), Some(USERS[2].into()));
+
+    // the user role expires
+    sys.spend_blocks(20);
+    assert_eq!(user_of(0), None);
+
+    // the user role is cleared on transfer
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetUser {
+            token_id: 0,
+            user: USERS[2].into(),
+            expires: sys.block_timestamp() / 1000 + 100,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(!transfer(&nft, USERS[1], USERS[3], 0).main_failed());
+    assert_eq!(user_of(0), None);
+
+    // Paid rental
+    let terms = Some(RentalTerms {
+        price_per_second: 1_000_000_000,
+        max_duration: 1_000,
+    });
+    let res = nft.send(USERS[1], NftAction::SetRentalTerms { token_id: 1, terms });
+    let message = NftEvent::RentalTermsChanged { token_id: 1, terms }.encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    // must fail since the duration exceeds the maximum
+    let res = nft.send_with_value(
+        USERS[4],
+        NftAction::Rent {
+            token_id: 1,
+            duration: 1_001,
+        },
+        2_000_000_000_000,
+    );
+    assert!(res.main_failed());
+
+    let owner_balance = sys.balance_of(USERS[1]);
+    let res = nft.send_with_value(
+        USERS[4],
+        NftAction::Rent {
+            token_id: 1,
+            duration: 500,
+        },
+        500_000_000_000,
+    );
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), owner_balance + 500_000_000_000);
+    let user = user_of(1).expect("Unexpected invalid user.");
+    assert_eq!(user.user, USERS[4].into());
+    assert!(user.rented);
+
+    // must fail since the rental can't be replaced before it expires
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetUser {
+            token_id: 1,
+            user: USERS[2].into(),
+            expires: user.expires,
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since the paid rental can't be voided
+    assert!(transfer(&nft, USERS[1], USERS[2], 1).main_failed());
+    assert!(burn(&nft, USERS[1], 1).main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::List {
+            token_id: 1,
+            price: 1_000_000_000_000,
+            expires_at: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    // the rental runs for 500 seconds
+    sys.spend_blocks(600);
+    assert_eq!(user_of(1), None);
+    assert!(!burn(&nft, USERS[1], 1).main_failed());
+}
+
+#[test]
+fn test_delegation() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+
+    let is_delegate = |delegate: u64, token_id| {
+        let reply = nft
+            .read_state(StateQuery::IsDelegate {
+                owner: USERS[1].into(),
+                delegate: delegate.into(),
+                token_id,
+            })
+            .expect("Unexpected invalid reply.");
+        let StateReply::IsDelegate(is_delegate) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        is_delegate
+    };
+
+    // must fail since the token belongs to another account
+    let res = nft.send(
+        USERS[2],
+        NftAction::Delegate {
+            delegate: USERS[3].into(),
+            token_ids: Some(vec![0]),
+            expires_at: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[1],
+        NftAction::Delegate {
+            delegate: USERS[2].into(),
+            token_ids: Some(vec![1]),
+            expires_at: None,
+        },
+    );
+    let message = NftEvent::Delegated {
+        owner: USERS[1].into(),
+        delegate: USERS[2].into(),
+        token_ids: Some(vec![1]),
+        expires_at: None,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    let expires_at = Some(sys.block_timestamp() / 1000 + 10);
+    let res = nft.send(
+        USERS[1],
+        NftAction::Delegate {
+            delegate: USERS[3].into(),
+            token_ids: None,
+            expires_at,
+        },
+    );
+    assert!(!res.main_failed());
+
+    assert!(is_delegate(USERS[2], Some(1)));
+    assert!(!is_delegate(USERS[2], Some(0)));
+    assert!(!is_delegate(USERS[2], None));
+    assert!(is_delegate(USERS[3], Some(0)));
+    assert!(is_delegate(USERS[3], None));
+
+    let reply = nft
+        .read_state(StateQuery::DelegatesOf {
+            owner: USERS[1].into(),
+        })
+        .expect("Unexpected invalid reply.");
+    let StateReply::DelegatesOf(delegations) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(delegations.len(), 2);
+
+    // the delegation expires
+    sys.spend_blocks(20);
+    assert!(!is_delegate(USERS[3], None));
+
+    // the delegation of a transferred token is dropped for good
+    assert!(!transfer(&nft, USERS[1], USERS[4], 1).main_failed());
+    assert!(!is_delegate(USERS[2], Some(1)));
+    assert!(!transfer(&nft, USERS[4], USERS[1], 1).main_failed());
+    assert!(!is_delegate(USERS[2], Some(1)));
+
+    // redelegating prunes the expired delegations
+    let res = nft.send(
+        USERS[1],
+        NftAction::Delegate {
+            delegate: USERS[2].into(),
+            token_ids: Some(vec![1]),
+            expires_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    let state = get_state(&nft).expect("Unexpected invalid state.");
+    assert_eq!(state.delegations[0].1.len(), 1);
+
+    let res = nft.send(
+        USERS[1],
+        NftAction::RevokeDelegate {
+            delegate: USERS[2].into(),
+        },
+    );
+    let message = NftEvent::DelegateRevoked {
+        owner: USERS[1].into(),
+        delegate: USERS[2].into(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert!(!is_delegate(USERS[2], Some(1)));
+}
+
+#[test]
+fn test_nesting() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    let nest = |from: u64, token_id, parent| {
+        nft.send(
+            from,
+            NftAction::Nest {
+                token_id,
+                parent: TokenParent {
+                    collection: None,
+                    token_id: parent,
+                },
+            },
+        )
+    };
+    let children = |token_id| {
+        let reply = nft
+            .read_state(StateQuery::Children { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::Children(children) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        children
+    };
+    let root_owner = |token_id| {
+        let reply = nft
+            .read_state(StateQuery::RootOwner { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::RootOwner(root_owner) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        root_owner
+    };
+
+    // Not authorized test
+    assert!(nest(USERS[2], 1, 0).main_failed());
+    let res = nest(USERS[1], 1, 0);
+    let message = NftEvent::Nested {
+        token_id: 1,
+        parent: TokenParent {
+            collection: None,
+            token_id: 0,
+        },
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert!(!nest(USERS[1], 2, 1).main_failed());
+    // must fail since the token would end up inside itself
+    assert!(nest(USERS[1], 0, 2).main_failed());
+    // must fail since the collection isn't whitelisted
+    let res = nft.send(
+        USERS[1],
+        NftAction::Nest {
+            token_id: 0,
+            parent: TokenParent {
+                collection: Some(USERS[4].into()),
+                token_id: 0,
+            },
+        },
+    );
+    assert!(res.main_failed());
+    assert_eq!(children(0), vec![1]);
+    assert_eq!(children(1), vec![2]);
+
+    // must fail since nested tokens move with their parent only
+    assert!(transfer(&nft, USERS[1], USERS[2], 2).main_failed());
+    assert!(burn(&nft, USERS[1], 0).main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::Lock {
+            token_id: 1,
+            until: u64::MAX,
+            locker: USERS[1].into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since the listed bundle can't change
+    let res = nft.send(
+        USERS[1],
+        NftAction::List {
+            token_id: 0,
+            price: 1_000_000_000_000,
+            expires_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(nft
+        .send(USERS[1], NftAction::Unnest { token_id: 2 })
+        .main_failed());
+    assert!(!nft
+        .send(USERS[1], NftAction::Delist { token_id: 0 })
+        .main_failed());
+
+    assert!(!transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert_eq!(root_owner(2), Some(RootOwner::Account(USERS[2].into())));
+    let state = get_state(&nft).unwrap();
+    for token_id in 0..3 {
+        assert!(state.owner_by_id.contains(&(token_id, USERS[2].into())));
+    }
+
+    // only the root owner can pull a child out
+    let res = nft.send(USERS[1], NftAction::Unnest { token_id: 1 });
+    assert!(res.main_failed());
+    let res = nft.send(USERS[2], NftAction::Unnest { token_id: 1 });
+    let message = NftEvent::Unnested {
+        token_id: 1,
+        parent: TokenParent {
+            collection: None,
+            token_id: 0,
+        },
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+    assert!(children(0).is_empty());
+    assert_eq!(children(1), vec![2]);
+    assert!(!transfer(&nft, USERS[2], USERS[1], 1).main_failed());
+    assert_eq!(root_owner(2), Some(RootOwner::Account(USERS[1].into())));
+}
+
+#[test]
+fn test_foreign_nesting() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    let collection = sys.get_program(2);
+    let collection_id: u64 = 2;
+    assert!(!buy(&nft, USERS[1], 1).main_failed());
+    assert!(!buy(&collection, USERS[1], 1).main_failed());
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetNestingCollection {
+            collection: collection_id.into(),
+            allowed: true,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the token stays with its owner since the parent doesn't exist
+    let missing = TokenParent {
+        collection: Some(collection_id.into()),
+        token_id: 5,
+    };
+    let res = nft.send(
+        USERS[1],
+        NftAction::Nest {
+            token_id: 0,
+            parent: missing,
+        },
+    );
+    let message = NftEvent::NestRejected {
+        token_id: 0,
+        parent: missing,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, USERS[1].into())));
+    assert!(state.token_parents.is_empty());
+
+    let parent = TokenParent {
+        collection: Some(collection_id.into()),
+        token_id: 0,
+    };
+    let res = nft.send(
+        USERS[1],
+        NftAction::Nest {
+            token_id: 0,
+            parent,
+        },
+    );
+    let message = NftEvent::Nested {
+        token_id: 0,
+        parent,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, collection_id.into())));
+
+    // the owner of the parent pulls the token out
+    let res = nft.send(USERS[1], NftAction::Unnest { token_id: 0 });
+    let message = NftEvent::Unnested {
+        token_id: 0,
+        parent,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+    let state = get_state(&nft).unwrap();
+    assert!(state.owner_by_id.contains(&(0, USERS[1].into())));
+}
+
+#[test]
+fn test_staking() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    let paying: u64 = 100;
+    let failing: u64 = 101;
+    gtest::Program::mock_with_id(&sys, paying, RewardToken { pay: true });
+    gtest::Program::mock_with_id(&sys, failing, RewardToken { pay: false });
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    let stake_info = || {
+        let reply = nft
+            .read_state(StateQuery::StakeInfo {
+                owner: USERS[1].into(),
+            })
+            .expect("Unexpected invalid reply.");
+        let StateReply::StakeInfo(stake_info) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        stake_info
+    };
+
+    // Not authorized test
+    let res = nft.send(USERS[1], NftAction::SetStakingRate { rate: 10 });
+    assert!(res.main_failed());
+    let res = nft.send(USERS[0], NftAction::SetStakingRate { rate: 10 });
+    let message = NftEvent::StakingRateChanged { rate: 10 }.encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    // must fail since the token belongs to another account
+    let res = nft.send(USERS[2], NftAction::Stake { token_ids: vec![0] });
+    assert!(res.main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::Stake {
+            token_ids: vec![0, 1],
+        },
+    );
+    let message = NftEvent::Staked {
+        owner: USERS[1].into(),
+        token_ids: vec![0, 1],
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], message)));
+
+    // must fail since staked tokens stay in place
+    assert!(transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+    assert!(burn(&nft, USERS[1], 1).main_failed());
+    assert!(!transfer(&nft, USERS[1], USERS[2], 2).main_failed());
+
+    sys.spend_blocks(10);
+    let staked = stake_info();
+    assert_eq!(staked.token_ids, vec![0, 1]);
+    assert!(staked.points >= 2 * 10 * 10);
+
+    // must fail since the program isn't frozen, the accrued points stay
+    assert!(nft.send(USERS[0], NftAction::Unfreeze {}).main_failed());
+    assert!(stake_info().points >= staked.points);
+
+    let res = nft.send(USERS[1], NftAction::Unstake { token_ids: vec![0] });
+    assert!(!res.main_failed());
+    assert!(!transfer(&nft, USERS[1], USERS[2], 0).main_failed());
+
+    // must fail since rewards are not paid out
+    let claim = NftAction::ClaimRewards {
+        owner: USERS[1].into(),
+    };
+    assert!(nft.send(USERS[1], claim).main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetRewardToken {
+            reward_token: Some(failing.into()),
+        },
+    );
+    assert!(!res.main_failed());
+    let res = nft.send(
+        USERS[1],
+        NftAction::ClaimRewards {
+            owner: USERS[1].into(),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(stake_info().points > staked.points);
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetRewardToken {
+            reward_token: Some(paying.into()),
+        },
+    );
+    assert!(!res.main_failed());
+    // Not authorized test
+    let res = nft.send(
+        USERS[3],
+        NftAction::ClaimRewards {
+            owner: USERS[1].into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    // a delegate claims for the owner
+    let res = nft.send(
+        USERS[1],
+        NftAction::Delegate {
+            delegate: USERS[3].into(),
+            token_ids: None,
+            expires_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(!nft
+        .send(USERS[1], NftAction::Unstake { token_ids: vec![1] })
+        .main_failed());
+    let points = stake_info().points;
+    let res = nft.send(
+        USERS[3],
+        NftAction::ClaimRewards {
+            owner: USERS[1].into(),
+        },
+    );
+    let message = NftEvent::RewardsClaimed {
+        owner: USERS[1].into(),
+        amount: points,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[3], message)));
+    assert_eq!(stake_info(), StakeInfo::default());
+}
+
+#[test]
+fn test_token_metadata_updates() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 2).main_failed());
+
+    let metadata = TokenMetadata {
+        name: "Kitty #0".to_string(),
+        description: "Revealed".to_string(),
+        media: "ipfs://media/0".to_string(),
+        reference: "ipfs://json/0".to_string(),
+    };
+    // Not authorized test
+    let res = nft.send(
+        USERS[2],
+        NftAction::SetTokenMetadata {
+            token_id: 0,
+            metadata: metadata.clone(),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetMetadataManager {
+            manager: USERS[2].into(),
+        },
+    );
+    let message = NftEvent::MetadataManagerChanged {
+        manager: USERS[2].into(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    let res = nft.send(
+        USERS[2],
+        NftAction::SetTokenMetadata {
+            token_id: 0,
+            metadata,
+        },
+    );
+    let message = NftEvent::MetadataUpdated { token_id: 0 }.encode();
+    assert!(res.contains(&(USERS[2], message)));
+    let token_meta = get_token_meta(&nft, 0).unwrap();
+    assert_eq!(token_meta.name, "Kitty #0");
+    assert_eq!(token_meta.description, "Revealed");
+    assert_eq!(token_meta.media, "ipfs://media/0");
+    assert_eq!(token_meta.reference, "ipfs://json/0");
+
+    // an empty reference keeps the token URI
+    let res = nft.send(
+        USERS[2],
+        NftAction::SetTokenMetadata {
+            token_id: 0,
+            metadata: TokenMetadata {
+                name: "Kitty #0".to_string(),
+                ..Default::default()
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    assert_eq!(get_token_meta(&nft, 0).unwrap().reference, "ipfs://json/0");
+
+    let res = nft.send(
+        USERS[2],
+        NftAction::RefreshMetadata {
+            from_token_id: 0,
+            to_token_id: 1,
+        },
+    );
+    let message = NftEvent::BatchMetadataUpdated {
+        from_token_id: 0,
+        to_token_id: 1,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[2], message)));
+
+    // must fail since owner updates are disabled
+    let set_name = |from: u64, name: &str| {
+        nft.send(
+            from,
+            NftAction::SetDisplayName {
+                token_id: 1,
+                name: name.to_string(),
+            },
+        )
+    };
+    assert!(set_name(USERS[1], "Tom").main_failed());
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetOwnerMetadataUpdates { enabled: true },
+    );
+    assert!(!res.main_failed());
+
+    let res = set_name(USERS[1], "Tom");
+    let message = NftEvent::MetadataUpdated { token_id: 1 }.encode();
+    assert!(res.contains(&(USERS[1], message)));
+    assert_eq!(get_token_meta(&nft, 1).unwrap().name, "Tom");
+    // must fail since the name is too long
+    let name = "a".repeat(DISPLAY_NAME_MAX_LEN + 1);
+    assert!(set_name(USERS[1], &name).main_failed());
+    // Not authorized test
+    assert!(set_name(USERS[3], "Jerry").main_failed());
+
+    // a delegate of the token holder can rename it
+    let res = nft.send(
+        USERS[1],
+        NftAction::Delegate {
+            delegate: USERS[3].into(),
+            token_ids: Some(vec![1]),
+            expires_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(!set_name(USERS[3], "Jerry").main_failed());
+    assert_eq!(get_token_meta(&nft, 1).unwrap().name, "Jerry");
+}
+
+#[test]
+fn test_attributes() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    assert!(!buy(&nft, USERS[1], 3).main_failed());
+
+    let attribute = |key: &str, value| Attribute {
+        key: key.to_string(),
+        value,
+    };
+    let background = |color: &str| attribute("Background", AttributeValue::Text(color.to_string()));
+    let attributes = vec![
+        (
+            0,
+            vec![
+                background("Blue"),
+                attribute("Level", AttributeValue::Number(5)),
+            ],
+        ),
+        (1, vec![background("Blue")]),
+        (2, vec![background("Gold")]),
+    ];
+
+    // Not authorized test
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetAttributes {
+            attributes: attributes.clone(),
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the keys repeat
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetAttributes {
+            attributes: vec![(0, vec![background("Blue"), background("Gold")])],
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(USERS[0], NftAction::SetAttributes { attributes });
+    let message = NftEvent::AttributesSet {
+        token_ids: vec![0, 1, 2],
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+
+    let reply = nft
+        .read_state(StateQuery::TokenAttributes { token_id: 0 })
+        .expect("Unexpected invalid reply.");
+    let StateReply::TokenAttributes(token_attributes) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(
+        token_attributes,
+        vec![
+            background("Blue"),
+            attribute("Level", AttributeValue::Number(5))
+        ]
+    );
+
+    let reply = nft
+        .read_state(StateQuery::TraitCounts {
+            key: "Background".to_string(),
+        })
+        .expect("Unexpected invalid reply.");
+    let StateReply::TraitCounts(counts) = reply else {
+        panic!("Unexpected invalid reply.");
+    };
+    assert_eq!(
+        counts,
+        vec![
+            (AttributeValue::Text("Blue".to_string()), 2),
+            (AttributeValue::Text("Gold".to_string()), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_token_uri() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    let res = buy(&nft, USERS[0], 2);
+    assert!(!res.main_failed());
+
+    let token_uri = |token_id: TokenId| {
+        let reply = nft
+            .read_state(StateQuery::TokenUri { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::TokenUri(uri) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        uri
+    };
+
+    // Not authorized test
+    let res = nft.send(
+        USERS[1],
+        NftAction::SetUriTemplate {
+            suffix: ".json".to_string(),
+            padding: 4,
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the padding exceeds the maximum
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetUriTemplate {
+            suffix: ".json".to_string(),
+            padding: URI_PADDING_MAX + 1,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetUriTemplate {
+            suffix: ".json".to_string(),
+            padding: 4,
+        },
+    );
+    let message = NftEvent::UriTemplateChanged {
+        suffix: ".json".to_string(),
+        padding: 4,
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert_eq!(
+        token_uri(1),
+        Some("https://mynft-test.com/0001.json".to_string())
+    );
+    assert_eq!(token_uri(5), None);
+
+    let uri = Some("ipfs://folder/special.json".to_string());
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenUri {
+            token_id: 1,
+            uri: uri.clone(),
+        },
+    );
+    let message = NftEvent::TokenUriChanged {
+        token_id: 1,
+        uri: uri.clone(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert_eq!(token_uri(1), uri);
+    let metadata = get_token_meta(&nft, 1).expect("Token metadata must exist");
+    assert_eq!(Some(metadata.reference), uri);
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenUri {
+            token_id: 1,
+            uri: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert_eq!(
+        token_uri(1),
+        Some("https://mynft-test.com/0001.json".to_string())
+    );
+}
+
+#[test]
+fn test_metadata_freeze() {
+    let sys = System::new();
+    init_nft_airdrop(&sys);
+    let nft = sys.get_program(1);
+    let res = buy(&nft, USERS[0], 2);
+    assert!(!res.main_failed());
+
+    let metadata_frozen = |token_id: TokenId| {
+        let reply = nft
+            .read_state(StateQuery::TokenInfo { token_id })
+            .expect("Unexpected invalid reply.");
+        let StateReply::TokenInfo(Some(token_info)) = reply else {
+            panic!("Unexpected invalid reply.");
+        };
+        token_info.metadata_frozen
+    };
+
+    // Not authorized test
+    let res = nft.send(USERS[1], NftAction::FreezeMetadata { token_id: 0 });
+    assert!(res.main_failed());
+
+    let res = nft.send(USERS[0], NftAction::FreezeMetadata { token_id: 0 });
+    let message = NftEvent::MetadataFrozen {
+        token_id: 0,
+        uri: "https://mynft-test.com/0".to_string(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert!(metadata_frozen(0));
+    assert!(!metadata_frozen(1));
+
+    // must fail since the metadata of the token is frozen
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenMetadata {
+            token_id: 0,
+            metadata: TokenMetadata::default(),
+        },
+    );
+    assert!(res.main_failed());
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenUri {
+            token_id: 0,
+            uri: Some("ipfs://swapped".to_string()),
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the base URI still builds the URI of the frozen token
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetBaseUri {
+            base_uri: "ipfs://swapped/".to_string(),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenUri {
+            token_id: 1,
+            uri: Some("ipfs://folder/1.json".to_string()),
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = nft.send(USERS[0], NftAction::FreezeAllMetadata {});
+    assert!(res.contains(&(USERS[0], NftEvent::AllMetadataFrozen.encode())));
+    assert!(metadata_frozen(1));
+    assert!(get_program_info(&nft).unwrap().metadata_frozen);
+    // must fail since the metadata is already frozen
+    let res = nft.send(USERS[0], NftAction::FreezeAllMetadata {});
+    assert!(res.main_failed());
+    let res = nft.send(
+        USERS[0],
+        NftAction::SetTokenUri {
+            token_id: 1,
+            uri: None,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn test_update_collection() {
+    let sys = System::new();
+    init_nft(&sys);
+    let nft = sys.get_program(1);
+
+    let social_link = |platform: &str, url: &str| SocialLink {
+        platform: platform.to_string(),
+        url: url.to_string(),
+    };
+    let update = || CollectionUpdate {
+        banner: Some("https://mynft-test.com/banner.png".to_string()),
+        logo: Some("ipfs://logo.png".to_string()),
+        social_links: Some(vec![social_link("twitter", "https://x.com/mynft")]),
+        category: Some("art".to_string()),
+        contract_uri: Some("https://mynft-test.com/contract.json".to_string()),
+        ..Default::default()
+    };
+
+    // Not authorized test
+    let res = nft.send(USERS[1], NftAction::UpdateCollection { update: update() });
+    assert!(res.main_failed());
+    // must fail since the link has no scheme
+    let res = nft.send(
+        USERS[0],
+        NftAction::UpdateCollection {
+            update: CollectionUpdate {
+                logo: Some("logo.png".to_string()),
+                ..update()
+            },
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the platform repeats
+    let res = nft.send(
+        USERS[0],
+        NftAction::UpdateCollection {
+            update: CollectionUpdate {
+                social_links: Some(vec![
+                    social_link("twitter", "https://x.com/mynft"),
+                    social_link("twitter", "https://x.com/other"),
+                ]),
+                ..update()
+            },
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = nft.send(USERS[0], NftAction::UpdateCollection { update: update() });
+    assert!(!res.main_failed());
+    let collection = get_state(&nft)
+        .expect("Unexpected invalid state.")
+        .collection;
+    let message = NftEvent::CollectionUpdated {
+        collection: collection.clone(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[0], message)));
+    assert_eq!(collection.name, "MyToken");
+    assert_eq!(collection.logo, "ipfs://logo.png");
+    assert_eq!(collection.external_link, "");
+    assert_eq!(collection.category, "art");
+    assert_eq!(
+        collection.social_links,
+        vec![social_link("twitter", "https://x.com/mynft")]
+    );
+
+    // the fields left out keep their values
+    let res = nft.send(
+        USERS[0],
+        NftAction::UpdateCollection {
+            update: CollectionUpdate {
+                external_link: Some("https://mynft-test.com".to_string()),
+                ..Default::default()
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let collection = get_state(&nft)
+        .expect("Unexpected invalid state.")
+        .collection;
+    assert_eq!(collection.external_link, "https://mynft-test.com");
+    assert_eq!(
+        collection.contract_uri,
+        "https://mynft-test.com/contract.json"
+    );
+}
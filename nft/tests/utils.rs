@@ -1,9 +1,83 @@
-use gstd::ActorId;
-use gtest::{Program, RunResult, System};
+use gstd::{ActorId, Decode, Encode};
+use gtest::{Program, RunResult, System, WasmProgram};
 use nft_io::*;
 
 const USERS: &[u64] = &[3, 4, 5, 6, 7];
 
+/// `SafeTransfer` receiver replying with a fixed answer
+#[derive(Debug)]
+pub struct NftReceiver {
+    pub accept: bool,
+}
+
+impl WasmProgram for NftReceiver {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        NftReceiverAction::decode(&mut &payload[..]).map_err(|_| "Unexpected payload")?;
+        let reply = if self.accept {
+            NftReceiverReply::Accepted
+        } else {
+            NftReceiverReply::Rejected
+        };
+
+        Ok(Some(reply.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(Vec::new())
+    }
+}
+
+/// Reward token program confirming or failing every `ClaimRewards` transfer
+#[derive(Debug)]
+pub struct RewardToken {
+    pub pay: bool,
+}
+
+impl WasmProgram for RewardToken {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let RewardTokenAction::Transfer { from, to, amount } =
+            RewardTokenAction::decode(&mut &payload[..]).map_err(|_| "Unexpected payload")?
+        else {
+            return Err("Unexpected action");
+        };
+        if !self.pay {
+            return Err("Insufficient balance");
+        }
+
+        Ok(Some(
+            RewardTokenEvent::Transfer { from, to, amount }.encode(),
+        ))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(Vec::new())
+    }
+}
+
 pub fn init_nft(sys: &System) {
     sys.init_logger();
     let nft: Program = Program::current_opt(sys);
@@ -13,6 +87,14 @@ pub fn init_nft(sys: &System) {
         description: String::from("My token"),
         symbol: String::from("My Symbol"),
         base_uri: String::from("https://mynft-test.com/"),
+        uri_suffix: String::new(),
+        uri_padding: 0,
+        banner: String::new(),
+        logo: String::new(),
+        external_link: String::new(),
+        social_links: vec![],
+        category: String::new(),
+        contract_uri: String::new(),
     };
 
     let init_nft = InitNft {
@@ -25,6 +107,16 @@ pub fn init_nft(sys: &System) {
             withdraw_wallet: USERS[4].into(),
             mint_limit: 50,
             sale_time: 0,
+            transferable: true,
+            royalty_receiver: USERS[0].into(),
+            royalty_bps: 0,
+            royalty_enforced: false,
+            platform_fee_bps: 0,
+            nesting_collections: vec![],
+            staking_rate: 0,
+            reward_token: None,
+            metadata_manager: ZERO_ID,
+            owner_metadata_updates: false,
         },
     };
 
@@ -42,6 +134,14 @@ pub fn init_nft_airdrop(sys: &System) {
         description: String::from("My token"),
         symbol: String::from("My Symbol"),
         base_uri: String::from("https://mynft-test.com/"),
+        uri_suffix: String::new(),
+        uri_padding: 0,
+        banner: String::new(),
+        logo: String::new(),
+        external_link: String::new(),
+        social_links: vec![],
+        category: String::new(),
+        contract_uri: String::new(),
     };
 
     let init_nft = InitNft {
@@ -54,6 +154,16 @@ pub fn init_nft_airdrop(sys: &System) {
             withdraw_wallet: USERS[4].into(),
             mint_limit: 5,
             sale_time: 0,
+            transferable: true,
+            royalty_receiver: USERS[0].into(),
+            royalty_bps: 0,
+            royalty_enforced: false,
+            platform_fee_bps: 0,
+            nesting_collections: vec![],
+            staking_rate: 0,
+            reward_token: None,
+            metadata_manager: ZERO_ID,
+            owner_metadata_updates: false,
         },
     };
 
@@ -76,17 +186,14 @@ pub fn buy(nft: &Program<'_>, member: u64, amount: u128) -> RunResult {
     nft.send_with_value(member, NftAction::Buy { amount }, required_funds)
 }
 
+/// Mints a single token to `to` through the owner's `Airdrop`
 pub fn mint(nft: &Program<'_>, member: u64, to: ActorId) -> RunResult {
     nft.send(
         member,
-        NftAction::Mint {
+        NftAction::Airdrop {
             to,
-            token_metadata: TokenMetadata {
-                name: "CryptoKitty".to_string(),
-                description: "Description".to_string(),
-                media: "http://".to_string(),
-                reference: "http://".to_string(),
-            },
+            amount: 1,
+            soulbound: false,
         },
     )
 }
@@ -135,6 +242,40 @@ pub fn approve(nft: &Program<'_>, from: u64, to: u64, token_id: u64) -> RunResul
         NftAction::Approve {
             to: to.into(),
             token_id: token_id.into(),
+            expires_at: None,
+        },
+    )
+}
+
+pub fn safe_transfer(
+    nft: &Program<'_>,
+    from: u64,
+    to: u64,
+    token_id: u64,
+    to_program: bool,
+) -> RunResult {
+    nft.send(
+        from,
+        NftAction::SafeTransfer {
+            to: to.into(),
+            token_id: token_id.into(),
+            data: Vec::new(),
+            to_program,
+        },
+    )
+}
+
+pub fn set_approval_for_all(
+    nft: &Program<'_>,
+    from: u64,
+    operator: u64,
+    approved: bool,
+) -> RunResult {
+    nft.send(
+        from,
+        NftAction::SetApprovalForAll {
+            operator: operator.into(),
+            approved,
         },
     )
 }